@@ -1,52 +1,136 @@
-use git2::Repository;
+use git2::{BranchType, Oid, Repository};
 use log::info;
+use serde::Serialize;
+use std::collections::HashSet;
 
+use crate::contributor::resolve_branch_head;
 use crate::utils::to_string;
 
+#[derive(Serialize, Debug)]
+pub struct BranchInfo {
+    pub name: String,
+    pub remote: bool,
+    /// Whether this is the branch HEAD points to, which the other branches are compared to.
+    pub default: bool,
+    pub tip: String,
+    pub tip_time: i64,
+    pub tip_author: String,
+    /// Commits on this branch that aren't on the default branch. None without a default branch.
+    pub ahead: Option<usize>,
+    /// Commits on the default branch that aren't on this branch.
+    pub behind: Option<usize>,
+    /// Whether the tip is already part of the default branch.
+    pub merged: Option<bool>,
+}
+
+/// Distinct author emails in the history of `branch`. Walks the whole history, so it is only
+/// counted for the branch selected in the overview rather than for every branch in
+/// `get_branch_names`.
 #[tauri::command(rename_all = "snake_case")]
-pub async fn get_branch_names(path: &str) -> Result<Vec<String>, String> {
-    let canonical_path = match std::path::Path::new(path).canonicalize() {
-        Ok(p) => {
-            println!("{}", p.to_str().unwrap());
-            p
+pub async fn count_branch_contributors(path: &str, branch: &str) -> Result<usize, String> {
+    let canonical_path = std::path::Path::new(path)
+        .canonicalize()
+        .map_err(to_string)?;
+    let repo = Repository::open(canonical_path).map_err(to_string)?;
+    let tip = resolve_branch_head(&repo, Some(branch))?;
+
+    let mut revwalk = repo.revwalk().map_err(to_string)?;
+    revwalk.push(tip).map_err(to_string)?;
+
+    let mut emails = HashSet::new();
+    for oid in revwalk {
+        let commit = repo
+            .find_commit(oid.map_err(to_string)?)
+            .map_err(to_string)?;
+        emails.insert(commit.author().email().unwrap_or_default().to_lowercase());
+    }
+    Ok(emails.len())
+}
+
+fn branch_info(
+    repo: &Repository,
+    name: String,
+    remote: bool,
+    default: bool,
+    tip: Oid,
+    default_tip: Option<Oid>,
+) -> Result<BranchInfo, String> {
+    let commit = repo.find_commit(tip).map_err(to_string)?;
+    let tip_author = commit.author().name().unwrap_or_default().to_string();
+
+    let (ahead, behind, merged) = match default_tip {
+        Some(default_tip) => {
+            let (ahead, behind) = repo
+                .graph_ahead_behind(tip, default_tip)
+                .map_err(to_string)?;
+            (Some(ahead), Some(behind), Some(ahead == 0))
         }
-        Err(e) => return Err(e.to_string()),
+        None => (None, None, None),
     };
 
+    Ok(BranchInfo {
+        default,
+        name,
+        remote,
+        tip: tip.to_string(),
+        tip_time: commit.time().seconds(),
+        tip_author,
+        ahead,
+        behind,
+        merged,
+    })
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_branch_names(path: &str) -> Result<Vec<BranchInfo>, String> {
+    let canonical_path = std::path::Path::new(path)
+        .canonicalize()
+        .map_err(to_string)?;
+
     let repo = Repository::open(canonical_path).map_err(to_string)?;
-    let head = repo.head().map_err(to_string)?;
-    let head_str = head.shorthand().unwrap_or("");
+    // A repository without commits has no default branch to compare to
+    let head = repo.head().ok();
+    let head_str = head
+        .as_ref()
+        .and_then(|h| h.shorthand())
+        .unwrap_or("")
+        .to_string();
+    let default_tip = head.as_ref().and_then(|h| h.target());
     let origin_head = format!("origin/{head_str}");
 
     info!("head: {head_str}");
     info!("origin head: {origin_head}");
 
-    let mut branches = repo
-        .branches(None)
-        .map_err(to_string)?
-        .map(|b| {
-            let (branch, _) = b.map_err(to_string).unwrap();
-            branch
-                .name()
-                .map_err(to_string)
-                .unwrap()
-                .unwrap()
-                .to_string()
-        })
-        .filter(|b| b.ne("origin/HEAD") && b.ne(origin_head.as_str())) // TODO: Should only removed
-        // copy of branch pointed to by HEAD for bare-clones
-        .collect::<Vec<String>>();
-
-    // Move HEAD name to start of list
-    if !head_str.is_empty() {
-        branches
-            .iter()
-            .position(|b| b.eq(head_str))
-            .iter()
-            .for_each(|head_idx| {
-                branches.remove(*head_idx);
-                branches.insert(0, head_str.to_string());
-            });
+    let mut branches = Vec::new();
+    for branch in repo.branches(None).map_err(to_string)? {
+        let (branch, branch_type) = branch.map_err(to_string)?;
+        let Some(name) = branch.name().map_err(to_string)? else {
+            log::warn!("Skipping branch with a name that isn't valid UTF-8");
+            continue;
+        };
+        // TODO: Should only removed copy of branch pointed to by HEAD for bare-clones
+        if name == "origin/HEAD" || name == origin_head {
+            continue;
+        }
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+
+        let remote = branch_type == BranchType::Remote;
+        branches.push(branch_info(
+            &repo,
+            name.to_string(),
+            remote,
+            !remote && name == head_str,
+            tip,
+            default_tip,
+        )?);
+    }
+
+    // Move HEAD to start of list
+    if let Some(head_idx) = branches.iter().position(|b| b.name == head_str) {
+        let head_branch = branches.remove(head_idx);
+        branches.insert(0, head_branch);
     }
 
     Ok(branches)
@@ -0,0 +1,324 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::manifest::get_working_directory;
+use crate::prompt_templates;
+use crate::settings::{read_settings, write_settings};
+use crate::summary_cache;
+use crate::utils::to_string;
+use crate::workspace::{self, Workspace};
+
+/*
+A marking bundle is a zip archive with this layout
+    bundle.json                     (BundleMetadata: workspace, presets, analysis, summaries)
+    cache/summaries/<key>.json      (cached AI summaries of the workspace's repositories)
+    repositories/<clone-dir>/...    (bare clones, only when exported with include_clones)
+*/
+
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const BUNDLE_METADATA_FILE: &str = "bundle.json";
+
+#[derive(Serialize, Deserialize)]
+struct PromptPreset {
+    name: String,
+    content: String,
+}
+
+// The summary settings the analysis was made with, so another marker gets the same summaries
+#[derive(Serialize, Deserialize, Default)]
+struct Presets {
+    #[serde(default)]
+    prompt_templates: Vec<PromptPreset>,
+    #[serde(default)]
+    active_prompt_template: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleMetadata {
+    format_version: u32,
+    app_version: String,
+    exported_at: String,
+    // Includes the repository entries with their email mappings and grading sheets,
+    // and the workspace's email mapping templates
+    workspace: Workspace,
+    #[serde(default)]
+    presets: Presets,
+    // Analysis results and summaries are held by the frontend, so they are passed through as-is
+    #[serde(default)]
+    analysis: Option<Value>,
+    #[serde(default)]
+    summaries: Option<Value>,
+    #[serde(default)]
+    includes_clones: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImportedBundle {
+    pub workspace_id: String,
+    pub repositories: usize,
+    pub restored_clones: usize,
+    pub restored_templates: usize,
+    pub analysis: Option<Value>,
+    pub summaries: Option<Value>,
+}
+
+fn add_dir_to_zip(
+    zip: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> Result<(), String> {
+    for entry in std::fs::read_dir(dir).map_err(to_string)? {
+        let entry = entry.map_err(to_string)?;
+        let path = entry.path();
+        let name = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+
+        if path.is_dir() {
+            zip.add_directory(name.as_str(), options)
+                .map_err(to_string)?;
+            add_dir_to_zip(zip, &path, &name, options)?;
+        } else {
+            zip.start_file(name.as_str(), options).map_err(to_string)?;
+            let mut file = File::open(&path).map_err(to_string)?;
+            std::io::copy(&mut file, zip).map_err(to_string)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_presets() -> Result<Presets, String> {
+    let prompt_templates = prompt_templates::list_prompt_templates()?
+        .into_iter()
+        .filter(|t| !t.builtin && t.error.is_none())
+        .map(|t| PromptPreset {
+            name: t.name,
+            content: t.content,
+        })
+        .collect();
+    Ok(Presets {
+        prompt_templates,
+        active_prompt_template: read_settings().prompt_template,
+    })
+}
+
+// Saves the bundle's prompt templates, keeping existing ones unless `overwrite` is set.
+// Returns how many were saved.
+fn restore_presets(presets: &Presets, overwrite: bool) -> Result<usize, String> {
+    let existing: Vec<String> = prompt_templates::list_prompt_templates()?
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+
+    let mut restored = 0;
+    for template in &presets.prompt_templates {
+        if existing.contains(&template.name) && !overwrite {
+            continue;
+        }
+        match prompt_templates::save_prompt_template(&template.name, &template.content) {
+            Ok(()) => restored += 1,
+            Err(e) => log::warn!(
+                "Skipping prompt template {} from bundle: {e}",
+                template.name
+            ),
+        }
+    }
+
+    let mut settings = read_settings();
+    if let Some(ref active) = presets.active_prompt_template {
+        if settings.prompt_template.is_none() || overwrite {
+            settings.prompt_template = Some(active.clone());
+            write_settings(&settings)?;
+        }
+    }
+    Ok(restored)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_marking_bundle(
+    workspace_id: &str,
+    destination: &str,
+    include_clones: Option<bool>,
+    analysis: Option<Value>,
+    summaries: Option<Value>,
+) -> Result<(), String> {
+    let include_clones = include_clones.unwrap_or(false);
+    let workspace = workspace::get_workspace(workspace_id).await?;
+    let clone_dirs: Vec<PathBuf> = if include_clones {
+        workspace
+            .repository
+            .iter()
+            .filter(|repo| repo.get("cloned").and_then(|c| c.as_bool()) == Some(true))
+            .filter_map(|repo| repo.get("path").and_then(|p| p.as_str()))
+            .map(PathBuf::from)
+            .filter(|path| path.is_dir())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Summaries of other workspaces don't belong in this marking context
+    let repo_paths: Vec<&str> = workspace
+        .repository
+        .iter()
+        .filter_map(|repo| repo.get("path").and_then(|p| p.as_str()))
+        .collect();
+    let cached_summaries = summary_cache::entries_for_repositories(&repo_paths).await;
+
+    let metadata = BundleMetadata {
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        workspace,
+        presets: read_presets()?,
+        analysis,
+        summaries,
+        includes_clones: include_clones,
+    };
+
+    let file = File::create(destination).map_err(|e| {
+        log::error!("Failed to create bundle at {destination}: {e}");
+        format!("Failed to create bundle: {e}")
+    })?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(BUNDLE_METADATA_FILE, options)
+        .map_err(to_string)?;
+    serde_json::to_writer_pretty(&mut zip, &metadata).map_err(to_string)?;
+
+    for path in cached_summaries {
+        if let Some(name) = path.file_name() {
+            let name = format!("cache/summaries/{}", name.to_string_lossy());
+            zip.start_file(name.as_str(), options).map_err(to_string)?;
+            let mut file = File::open(&path).map_err(to_string)?;
+            std::io::copy(&mut file, &mut zip).map_err(to_string)?;
+        }
+    }
+
+    for clone_dir in clone_dirs {
+        if let Some(name) = clone_dir.file_name() {
+            let prefix = format!("repositories/{}", name.to_string_lossy());
+            log::info!("Adding clone {clone_dir:?} to bundle");
+            add_dir_to_zip(&mut zip, &clone_dir, &prefix, options)?;
+        }
+    }
+
+    zip.finish().map_err(to_string)?;
+    log::info!("Exported marking bundle for workspace {workspace_id} to {destination}");
+    Ok(())
+}
+
+/// Restores a bundle created by `export_marking_bundle` as a new workspace. Clone paths are
+/// remapped into this machine's working directory; existing files are only replaced when
+/// `overwrite` is set.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_marking_bundle(
+    source: &str,
+    overwrite: Option<bool>,
+) -> Result<ImportedBundle, String> {
+    let overwrite = overwrite.unwrap_or(false);
+    let file = File::open(source).map_err(|e| format!("Failed to open bundle: {e}"))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid bundle: {e}"))?;
+
+    let metadata: BundleMetadata = {
+        let entry = archive
+            .by_name(BUNDLE_METADATA_FILE)
+            .map_err(|_| format!("Invalid bundle: missing {BUNDLE_METADATA_FILE}"))?;
+        serde_json::from_reader(entry).map_err(|e| format!("Invalid bundle metadata: {e}"))?
+    };
+
+    if metadata.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format version {} is newer than supported version {BUNDLE_FORMAT_VERSION}. Please update GitGauge.",
+            metadata.format_version
+        ));
+    }
+
+    let working_dir = PathBuf::from(get_working_directory().await);
+
+    // Where each cloned repository ends up on this machine
+    let mut workspace = metadata.workspace;
+    let mut new_paths: Vec<(String, String)> = Vec::new();
+    for repo in workspace.repository.iter_mut() {
+        let cloned = repo.get("cloned").and_then(|c| c.as_bool()) == Some(true);
+        let old_path = repo
+            .get("path")
+            .and_then(|p| p.as_str())
+            .map(PathBuf::from)
+            .unwrap_or_default();
+
+        if cloned {
+            if let Some(name) = old_path.file_name() {
+                let new_path = working_dir.join("repositories").join(name);
+                let new_path = new_path.to_string_lossy().to_string();
+                new_paths.push((old_path.to_string_lossy().to_string(), new_path.clone()));
+                repo["path"] = Value::String(new_path);
+            }
+        } else if !old_path.exists() {
+            log::warn!("Local repository {old_path:?} from bundle does not exist on this machine");
+        }
+    }
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(to_string)?;
+        // enclosed_name rejects absolute paths and `..` components
+        let Some(relative) = entry.enclosed_name() else {
+            log::warn!("Skipping unsafe bundle entry {}", entry.name());
+            continue;
+        };
+        if !relative.starts_with("cache") && !relative.starts_with("repositories") {
+            continue;
+        }
+
+        let target = working_dir.join(&relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target).map_err(to_string)?;
+            continue;
+        }
+        if target.exists() && !overwrite {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(to_string)?;
+        }
+
+        if relative.starts_with("cache") {
+            let mut content = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut content).map_err(to_string)?;
+            let content = summary_cache::remap_repo_path(&content, &new_paths).unwrap_or(content);
+            std::fs::write(&target, content).map_err(to_string)?;
+        } else {
+            let mut out = File::create(&target).map_err(to_string)?;
+            std::io::copy(&mut entry, &mut out).map_err(to_string)?;
+        }
+    }
+
+    let restored_clones = new_paths
+        .iter()
+        .filter(|(_, new_path)| Path::new(new_path).is_dir())
+        .count();
+    let restored_templates = restore_presets(&metadata.presets, overwrite)?;
+
+    workspace.archived = false;
+    let repositories = workspace.repository.len();
+    let workspace = workspace::add_workspace(workspace).await?;
+
+    log::info!(
+        "Imported marking bundle {source} as workspace {} ({repositories} repositories, {restored_clones} clones, {restored_templates} prompt templates)",
+        workspace.id
+    );
+
+    Ok(ImportedBundle {
+        workspace_id: workspace.id,
+        repositories,
+        restored_clones,
+        restored_templates,
+        analysis: metadata.analysis,
+        summaries: metadata.summaries,
+    })
+}
@@ -1,13 +1,15 @@
-use git2::{BranchType, Oid, Repository, Sort};
+use git2::{BranchType, Commit, Delta, Oid, Patch, Repository, Sort};
 use log::info;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
-use crate::utils::to_string;
+use crate::forge_activity::ForgeActivity;
+use crate::tags::read_tags;
+use crate::utils::{add_unique, to_string};
 
-fn generate_initials(name: &str) -> String {
+pub fn generate_initials(name: &str) -> String {
     name.split_whitespace()
         .map(|s| s.chars().next().unwrap_or('?').to_ascii_uppercase())
         .collect::<String>()
@@ -19,6 +21,15 @@ pub enum Contacts {
     EmailList(Vec<String>),
 }
 
+impl Contacts {
+    pub fn emails(&self) -> &[String] {
+        match self {
+            Contacts::Email(email) => std::slice::from_ref(email),
+            Contacts::EmailList(list) => list,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Contributor {
     pub username: String,
@@ -31,14 +42,29 @@ pub struct Contributor {
     pub total_regex_matches: usize,
     pub commits_matching_regex: u64,
     pub ai_summary: String,
+    /// Pull requests, reviews and issues from the git host (See forge_activity.rs).
+    #[serde(default)]
+    pub forge_activity: Option<ForgeActivity>,
+    /// Git host usernames of this contributor (See identity.rs).
+    #[serde(default)]
+    pub logins: Vec<String>,
+    /// Author names this contributor has committed under.
+    #[serde(default)]
+    pub names: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DateRange {
     pub start: i64,
     pub end: i64,
 }
 
+impl DateRange {
+    pub fn contains(&self, time: i64) -> bool {
+        time >= self.start && time <= self.end
+    }
+}
+
 #[tauri::command(rename_all = "snake_case")]
 pub async fn group_contributors_by_config(
     config_json: Value,
@@ -57,6 +83,9 @@ pub async fn group_contributors_by_config(
             let mut total_regex_matches = 0;
             let mut commits_matching_regex = 0;
             let ai_summary = String::new();
+            let mut forge_activity: Option<ForgeActivity> = None;
+            let mut logins: Vec<String> = Vec::new();
+            let mut names: Vec<String> = Vec::new();
 
             let mut processed_contributors = std::collections::HashSet::new();
 
@@ -76,10 +105,7 @@ pub async fn group_contributors_by_config(
                 // Second pass: find contributors that have ANY email in this group
                 // but ensure each contributor is only processed ONCE per group
                 for contrib in contributors.iter() {
-                    let contributor_emails = match &contrib.contacts {
-                        Contacts::Email(e) => vec![e.clone()],
-                        Contacts::EmailList(list) => list.clone(),
-                    };
+                    let contributor_emails = contrib.contacts.emails();
 
                     // Check if this contributor has any email that matches this group
                     let has_matching_email = contributor_emails
@@ -99,13 +125,21 @@ pub async fn group_contributors_by_config(
                             deletions += contrib.deletions;
                             total_regex_matches += contrib.total_regex_matches;
                             commits_matching_regex += contrib.commits_matching_regex;
+                            if let Some(ref activity) = contrib.forge_activity {
+                                forge_activity
+                                    .get_or_insert_with(ForgeActivity::default)
+                                    .add(activity);
+                            }
+                            add_unique(&mut logins, &contrib.logins);
+                            add_unique(&mut names, &contrib.names);
 
                             // Add all matching emails from this contributor to contacts
-                            for email in contributor_emails.iter() {
-                                if group_emails.contains(email) && !contacts.contains(email) {
-                                    contacts.push(email.clone());
-                                }
-                            }
+                            let matching: Vec<String> = contributor_emails
+                                .iter()
+                                .filter(|email| group_emails.contains(*email))
+                                .cloned()
+                                .collect();
+                            add_unique(&mut contacts, &matching);
                         }
                     }
                 }
@@ -127,6 +161,9 @@ pub async fn group_contributors_by_config(
                     total_regex_matches,
                     commits_matching_regex,
                     ai_summary,
+                    forge_activity,
+                    logins,
+                    names,
                 });
             }
         }
@@ -134,20 +171,11 @@ pub async fn group_contributors_by_config(
 
     // Add contributors not included in config
     for c in &contributors {
-        let mut is_grouped = false;
-        match &c.contacts {
-            Contacts::Email(email) => {
-                if grouped_emails.contains(email) {
-                    is_grouped = true;
-                }
-            }
-            Contacts::EmailList(list) => {
-                if list.iter().any(|e| grouped_emails.contains(e)) {
-                    is_grouped = true;
-                }
-            }
-        }
-
+        let is_grouped = c
+            .contacts
+            .emails()
+            .iter()
+            .any(|e| grouped_emails.contains(e));
         if !is_grouped {
             result.push(c.clone());
         }
@@ -156,6 +184,114 @@ pub async fn group_contributors_by_config(
     Ok(result)
 }
 
+/// Lines added and deleted by a commit, compared to its first parent.
+pub fn commit_line_changes(repo: &Repository, commit: &Commit) -> Result<(usize, usize), String> {
+    let commit_tree = commit.tree().map_err(to_string)?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(
+            commit
+                .parent(0)
+                .map_err(to_string)?
+                .tree()
+                .map_err(to_string)?,
+        )
+    } else {
+        None
+    };
+
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
+        .map_err(to_string)?;
+    let stats = diff.stats().map_err(to_string)?;
+    Ok((stats.insertions(), stats.deletions()))
+}
+
+// Adds a commit's line counts and regex matches to its author's entry
+fn add_commit_stats(
+    repo: &Repository,
+    commit: &Commit,
+    rgx: Option<&Regex>,
+    contributors: &mut HashMap<String, Contributor>,
+) -> Result<(), String> {
+    let author_signature = commit.author();
+    let email = author_signature.email().unwrap_or("").to_string();
+    let username = author_signature.name().unwrap_or("unknown");
+    let initials = generate_initials(username);
+    let profile_bg_colour = generate_profile_bg_colour(username);
+
+    let (additions, deletions) = commit_line_changes(repo, commit)?;
+    let (additions, deletions) = (additions as u64, deletions as u64);
+
+    let total_matches = if let Some(re) = rgx {
+        let commit_msg = commit.message_raw().unwrap_or("");
+        let id = commit.id().to_string().chars().take(6).collect::<String>();
+
+        re.find_iter(commit_msg)
+            .inspect(|m| {
+                info!("{id} :: {}", m.as_str());
+            })
+            .count()
+    } else {
+        0
+    };
+
+    let entry = contributors
+        .entry(username.to_string())
+        .or_insert_with(|| Contributor {
+            username: username.to_string(),
+            contacts: Contacts::EmailList(vec![email.clone()]),
+            total_commits: 0,
+            additions: 0,
+            deletions: 0,
+            profile_colour: profile_bg_colour,
+            username_initials: initials,
+            total_regex_matches: 0,
+            commits_matching_regex: 0,
+            ai_summary: String::from(""),
+            forge_activity: None,
+            logins: Vec::new(),
+            names: vec![username.to_string()],
+        });
+
+    // Add email to contacts if not already present
+    match &mut entry.contacts {
+        Contacts::EmailList(list) => {
+            if !list.contains(&email) {
+                list.push(email.clone());
+            }
+        }
+        Contacts::Email(existing) => {
+            if existing != &email {
+                *entry = Contributor {
+                    username: username.to_string(),
+                    contacts: Contacts::EmailList(vec![existing.clone(), email.clone()]),
+                    total_commits: entry.total_commits,
+                    additions: entry.additions,
+                    deletions: entry.deletions,
+                    profile_colour: entry.profile_colour.clone(),
+                    username_initials: entry.username_initials.clone(),
+                    total_regex_matches: entry.total_regex_matches,
+                    commits_matching_regex: entry.commits_matching_regex,
+                    ai_summary: String::from(""),
+                    forge_activity: entry.forge_activity.clone(),
+                    logins: entry.logins.clone(),
+                    names: entry.names.clone(),
+                };
+            }
+        }
+    }
+
+    entry.total_commits += 1;
+    entry.additions += additions;
+    entry.deletions += deletions;
+    entry.total_regex_matches += total_matches;
+
+    if total_matches > 0 {
+        entry.commits_matching_regex += 1;
+    }
+    Ok(())
+}
+
 // date_range: Option<(i64, i64)> - Optional date range in UNIX timestamp format
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_contributor_info(
@@ -180,39 +316,16 @@ pub async fn get_contributor_info(
         }
     };
 
-    let mut branches: Vec<String> = Vec::new();
-    for branch in repo.branches(None).map_err(to_string)? {
-        let (branch, _branch_type) = branch.map_err(to_string)?;
-        if let Some(name) = branch.name().map_err(to_string)? {
-            branches.push(name.to_string());
-        }
-    }
-
     // Resolve branch reference
     let mut revwalk = repo.revwalk().map_err(to_string)?;
-    let head = match branch {
-        Some(target) => {
-            // Ensure the branch exists before proceeding
-            if !branches.contains(&target.to_string()) {
-                log::error!("Branch: {target} not found in the repository.");
-                return Err(format!("Branch: {target} not found in the repository."));
-            }
-            find_branch_oid(&repo, target)?
-        }
-        None => repo
-            .head()
-            .map_err(to_string)?
-            .target()
-            .ok_or(git2::Error::from_str("Invalid HEAD"))
-            .map_err(to_string)?,
-    };
+    let head = resolve_branch_head(&repo, branch)?;
 
     revwalk.push(head).map_err(to_string)?;
     revwalk.set_sorting(Sort::TIME).map_err(to_string)?;
 
     let mut contributors: HashMap<String, Contributor> = HashMap::new();
 
-    let rgx = regex_query.map(|rgx_str| Regex::new(rgx_str).map_err(to_string));
+    let rgx = regex_query.map(Regex::new).transpose().map_err(to_string)?;
 
     for oid_result in revwalk {
         let oid = oid_result.map_err(to_string)?;
@@ -221,107 +334,261 @@ pub async fn get_contributor_info(
 
         if let Some(ref date_range) = date_range {
             // Check if commit time is within the specified date range
-            if time < date_range.start || time > date_range.end {
+            if !date_range.contains(time) {
                 continue;
             }
         }
 
-        let author_signature = commit.author();
-        let email = author_signature.email().unwrap_or("").to_string();
-        let username = author_signature.name().unwrap_or("unknown");
-        let initials = generate_initials(username);
-        let profile_bg_colour = generate_profile_bg_colour(username);
-
-        let commit_tree = commit.tree().map_err(to_string)?;
-
-        let parent_tree = if commit.parent_count() > 0 {
-            Some(
-                commit
-                    .parent(0)
-                    .map_err(to_string)?
-                    .tree()
-                    .map_err(to_string)?,
-            )
-        } else {
-            None
-        };
+        add_commit_stats(&repo, &commit, rgx.as_ref(), &mut contributors)?;
+    }
 
-        let diff = repo
-            .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)
-            .map_err(to_string)?;
+    Ok(contributors)
+}
+
+/// Contributions between one release tag and the next.
+#[derive(Serialize, Debug)]
+pub struct ReleaseContributions {
+    /// The tag that closes the window, or None for the commits made since the last tag.
+    pub tag: Option<String>,
+    pub previous_tag: Option<String>,
+    /// Commit time of the previous tag; None for the first release.
+    pub start: Option<i64>,
+    /// Commit time of the tag, or of the branch head for unreleased work.
+    pub end: i64,
+    pub contributors: HashMap<String, Contributor>,
+}
 
-        let stats = diff.stats().map_err(to_string)?;
-        let additions = stats.insertions() as u64;
-        let deletions = stats.deletions() as u64;
-
-        let total_matches = if regex_query.is_some() {
-            let commit_msg = commit.message_raw().unwrap_or("");
-            let id = commit.id().to_string().chars().take(6).collect::<String>();
-            let re = rgx.clone().unwrap()?;
-
-            re.find_iter(commit_msg)
-                .inspect(|m| {
-                    info!("{id} :: {}", m.as_str());
-                })
-                .count()
-        } else {
-            0
+/// Contributor stats for each release: the commits reachable from a tag but not from any
+/// earlier tag, for the tags in `branch`'s history. Commits after the last tag form a final
+/// window with no tag.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_contributor_info_by_release(
+    path: &str,
+    branch: Option<&str>,
+    regex_query: Option<&str>,
+) -> Result<Vec<ReleaseContributions>, String> {
+    let canonical_path = std::path::Path::new(path)
+        .canonicalize()
+        .map_err(to_string)?;
+    let repo = Repository::open(canonical_path).map_err(to_string)?;
+    let head = resolve_branch_head(&repo, branch)?;
+    let head_time = repo.find_commit(head).map_err(to_string)?.time().seconds();
+    let rgx = regex_query.map(Regex::new).transpose().map_err(to_string)?;
+
+    // Positions in a parents-first walk, so releases are ordered by ancestry rather than by
+    // commit clocks, which can run out of order
+    let mut history = repo.revwalk().map_err(to_string)?;
+    history.push(head).map_err(to_string)?;
+    history
+        .set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)
+        .map_err(to_string)?;
+    let positions = history
+        .enumerate()
+        .map(|(position, oid)| oid.map(|oid| (oid, position)))
+        .collect::<Result<HashMap<Oid, usize>, _>>()
+        .map_err(to_string)?;
+
+    // Tags in the branch's history, one per commit
+    let mut releases: Vec<(usize, Option<String>, Oid, i64)> = Vec::new();
+    for tag in read_tags(&repo)? {
+        let oid = Oid::from_str(&tag.commit).map_err(to_string)?;
+        let Some(&position) = positions.get(&oid) else {
+            continue;
         };
+        if !releases.iter().any(|(_, _, o, _)| *o == oid) {
+            releases.push((position, Some(tag.name), oid, tag.commit_time));
+        }
+    }
+    releases.sort_by_key(|(position, ..)| *position);
+    if releases.last().is_none_or(|(_, _, oid, _)| *oid != head) {
+        releases.push((positions.len(), None, head, head_time));
+    }
 
-        let entry = contributors
-            .entry(username.to_string())
-            .or_insert_with(|| Contributor {
-                username: username.to_string(),
-                contacts: Contacts::EmailList(vec![email.clone()]),
-                total_commits: 0,
-                additions: 0,
-                deletions: 0,
-                profile_colour: profile_bg_colour,
-                username_initials: initials,
-                total_regex_matches: 0,
-                commits_matching_regex: 0,
-                ai_summary: String::from(""),
-            });
-
-        // Add email to contacts if not already present
-        match &mut entry.contacts {
-            Contacts::EmailList(list) => {
-                if !list.contains(&email) {
-                    list.push(email.clone());
-                }
+    let mut windows = Vec::new();
+    let mut previous: Option<(String, i64)> = None;
+    // Every earlier release is hidden, as tags on branches merged later aren't ancestors of
+    // the tag just before them
+    let mut released: Vec<Oid> = Vec::new();
+    for (_, tag, oid, time) in releases {
+        let mut revwalk = repo.revwalk().map_err(to_string)?;
+        revwalk.push(oid).map_err(to_string)?;
+        for released_oid in &released {
+            revwalk.hide(*released_oid).map_err(to_string)?;
+        }
+
+        let mut contributors = HashMap::new();
+        for commit_oid in revwalk {
+            let commit = repo
+                .find_commit(commit_oid.map_err(to_string)?)
+                .map_err(to_string)?;
+            add_commit_stats(&repo, &commit, rgx.as_ref(), &mut contributors)?;
+        }
+
+        windows.push(ReleaseContributions {
+            tag: tag.clone(),
+            previous_tag: previous.as_ref().map(|(name, _)| name.clone()),
+            start: previous.as_ref().map(|(_, time)| *time),
+            end: time,
+            contributors,
+        });
+        previous = tag.map(|name| (name, time));
+        released.push(oid);
+    }
+
+    Ok(windows)
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChangedFile {
+    pub path: String,
+    /// "added", "deleted", "modified", "renamed" or "copied"
+    pub status: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// What a branch adds on top of another, as a pull request would show it.
+#[derive(Serialize, Debug)]
+pub struct BranchComparison {
+    pub base: String,
+    pub head: String,
+    pub merge_base: String,
+    pub commits: usize,
+    pub contributors: HashMap<String, Contributor>,
+    /// Net changes from the merge base to the head.
+    pub changed_files: Vec<ChangedFile>,
+}
+
+// Branches first, then anything else git understands, such as tags and commit hashes
+fn resolve_ref(repo: &Repository, name: &str) -> Result<Oid, String> {
+    find_branch_oid(repo, name).or_else(|branch_error| {
+        repo.revparse_single(name)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .map_err(|_| branch_error)
+    })
+}
+
+fn changed_files(repo: &Repository, from: Oid, to: Oid) -> Result<Vec<ChangedFile>, String> {
+    let from_tree = repo
+        .find_commit(from)
+        .and_then(|c| c.tree())
+        .map_err(to_string)?;
+    let to_tree = repo
+        .find_commit(to)
+        .and_then(|c| c.tree())
+        .map_err(to_string)?;
+    let mut diff = repo
+        .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
+        .map_err(to_string)?;
+    diff.find_similar(None).map_err(to_string)?;
+
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let status = match delta.status() {
+            Delta::Added => "added",
+            Delta::Deleted => "deleted",
+            Delta::Renamed => "renamed",
+            Delta::Copied => "copied",
+            _ => "modified",
+        };
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // Binary files have no patch
+        let (additions, deletions) = match Patch::from_diff(&diff, index).map_err(to_string)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats().map_err(to_string)?;
+                (additions, deletions)
             }
-            Contacts::Email(existing) => {
-                if existing != &email {
-                    *entry = Contributor {
-                        username: username.to_string(),
-                        contacts: Contacts::EmailList(vec![existing.clone(), email.clone()]),
-                        total_commits: entry.total_commits,
-                        additions: entry.additions,
-                        deletions: entry.deletions,
-                        profile_colour: entry.profile_colour.clone(),
-                        username_initials: entry.username_initials.clone(),
-                        total_regex_matches: entry.total_regex_matches,
-                        commits_matching_regex: entry.commits_matching_regex,
-                        ai_summary: String::from(""),
-                    };
+            None => (0, 0),
+        };
+        files.push(ChangedFile {
+            path,
+            status: status.to_string(),
+            additions,
+            deletions,
+        });
+    }
+    Ok(files)
+}
+
+/// Compares `head` with `base` (e.g. "feature/x" and "main"): the commits reachable from
+/// `head` but not from `base`, who made them and which files changed since their merge base.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn compare_branches(
+    path: &str,
+    base: &str,
+    head: &str,
+) -> Result<BranchComparison, String> {
+    let canonical_path = std::path::Path::new(path)
+        .canonicalize()
+        .map_err(to_string)?;
+    let repo = Repository::open(canonical_path).map_err(to_string)?;
+    let base_oid = resolve_ref(&repo, base)?;
+    let head_oid = resolve_ref(&repo, head)?;
+    let merge_base = repo
+        .merge_base(base_oid, head_oid)
+        .map_err(|e| format!("{base} and {head} have no common history: {e}"))?;
+
+    // Hiding base rather than the merge base, as criss-cross merges leave several merge bases
+    // and the one used for the file diff doesn't hide the others' history
+    let mut revwalk = repo.revwalk().map_err(to_string)?;
+    revwalk.push(head_oid).map_err(to_string)?;
+    revwalk.hide(base_oid).map_err(to_string)?;
+
+    let mut commits = 0;
+    let mut contributors = HashMap::new();
+    for oid in revwalk {
+        let commit = repo
+            .find_commit(oid.map_err(to_string)?)
+            .map_err(to_string)?;
+        add_commit_stats(&repo, &commit, None, &mut contributors)?;
+        commits += 1;
+    }
+
+    Ok(BranchComparison {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base: merge_base.to_string(),
+        commits,
+        contributors,
+        changed_files: changed_files(&repo, merge_base, head_oid)?,
+    })
+}
+
+/// Resolves the commit an analysis walks back from: the tip of `branch` if given, otherwise HEAD.
+pub fn resolve_branch_head(repo: &Repository, branch: Option<&str>) -> Result<Oid, String> {
+    match branch {
+        Some(target) => {
+            let mut branches: Vec<String> = Vec::new();
+            for branch in repo.branches(None).map_err(to_string)? {
+                let (branch, _branch_type) = branch.map_err(to_string)?;
+                if let Some(name) = branch.name().map_err(to_string)? {
+                    branches.push(name.to_string());
                 }
             }
-        }
-
-        entry.total_commits += 1;
-        entry.additions += additions;
-        entry.deletions += deletions;
-        entry.total_regex_matches += total_matches;
 
-        if total_matches > 0 {
-            entry.commits_matching_regex += 1;
+            // Ensure the branch exists before proceeding
+            if !branches.contains(&target.to_string()) {
+                log::error!("Branch: {target} not found in the repository.");
+                return Err(format!("Branch: {target} not found in the repository."));
+            }
+            find_branch_oid(repo, target)
         }
+        None => repo
+            .head()
+            .map_err(to_string)?
+            .target()
+            .ok_or(git2::Error::from_str("Invalid HEAD"))
+            .map_err(to_string),
     }
-
-    Ok(contributors)
 }
 
-fn find_branch_oid(repo: &Repository, branch: &str) -> Result<Oid, String> {
+pub fn find_branch_oid(repo: &Repository, branch: &str) -> Result<Oid, String> {
     // Try local branch first
     if let Ok(branch_ref) = repo.find_branch(branch, BranchType::Local) {
         return branch_ref
@@ -339,7 +606,7 @@ fn find_branch_oid(repo: &Repository, branch: &str) -> Result<Oid, String> {
     Err(format!("Branch '{branch}' not found as local or remote"))
 }
 
-fn generate_profile_bg_colour(username: &str) -> String {
+pub fn generate_profile_bg_colour(username: &str) -> String {
     let hash = username.as_bytes().iter().fold(0usize, |hash, byte| {
         (*byte as usize) + (hash << 5).wrapping_sub(hash)
     });
@@ -0,0 +1,386 @@
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::git_hosts::{self, HostKind};
+use crate::settings::data_directory;
+use crate::utils::to_string;
+
+/*
+credentials.json lives in the data directory and is this format
+{
+    "version": 1,
+    "salt": "<base64>",
+    "nonce": "<base64>",
+    "ciphertext": "<base64>"
+}
+The ciphertext is the JSON of the entries, encrypted with ChaCha20-Poly1305 using a key derived
+from the user's passphrase with Argon2id. The passphrase itself is never stored; the derived key
+is kept in memory while the store is unlocked.
+    */
+
+const STORE_VERSION: u32 = 1;
+const SALT_LENGTH: usize = 16;
+// ChaCha20-Poly1305 uses 96-bit nonces
+const NONCE_LENGTH: usize = 12;
+
+pub const GEMINI_CREDENTIAL: &str = "gemini";
+pub const OPENAI_CREDENTIAL: &str = "openai_compatible";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    /// API key of an AI provider, named by provider (e.g. "gemini").
+    LlmApiKey,
+    /// Personal access token, named by git host (e.g. "github.com").
+    GitToken,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Credential {
+    kind: CredentialKind,
+    name: String,
+    secret: String,
+    updated_at: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CredentialInfo {
+    pub kind: CredentialKind,
+    pub name: String,
+    pub masked: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CredentialStoreStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedStore {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+struct UnlockedStore {
+    key: Key,
+    salt: Vec<u8>,
+    entries: BTreeMap<(CredentialKind, String), Credential>,
+}
+
+static STORE: RwLock<Option<UnlockedStore>> = RwLock::new(None);
+
+// Secrets entered while the store is locked, kept until the app closes
+static SESSION_SECRETS: RwLock<BTreeMap<(CredentialKind, String), String>> =
+    RwLock::new(BTreeMap::new());
+
+fn get_store_path() -> PathBuf {
+    data_directory().join("credentials.json")
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key from passphrase: {e}"))?;
+    Ok(key)
+}
+
+fn decrypt(encrypted: &EncryptedStore, key: &Key) -> Result<Vec<Credential>, String> {
+    let nonce = BASE64.decode(&encrypted.nonce).map_err(to_string)?;
+    // from_slice panics on any other length, e.g. for a truncated or edited store
+    if nonce.len() != NONCE_LENGTH {
+        return Err("The credential store is corrupted (invalid nonce)".to_string());
+    }
+    let ciphertext = BASE64.decode(&encrypted.ciphertext).map_err(to_string)?;
+    let plaintext = ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Incorrect passphrase".to_string())?;
+    serde_json::from_slice(&plaintext).map_err(to_string)
+}
+
+fn save(store: &UnlockedStore) -> Result<(), String> {
+    let entries: Vec<&Credential> = store.entries.values().collect();
+    let plaintext = serde_json::to_vec(&entries).map_err(to_string)?;
+    // A fresh nonce every time, as the same key encrypts every save
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&store.key)
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|e| format!("Failed to encrypt credentials: {e}"))?;
+
+    let encrypted = EncryptedStore {
+        version: STORE_VERSION,
+        salt: BASE64.encode(&store.salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    let path = get_store_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(to_string)?;
+    }
+    std::fs::write(
+        &path,
+        serde_json::to_string_pretty(&encrypted).map_err(to_string)?,
+    )
+    .map_err(|e| {
+        log::error!("Failed to save credential store: {e}");
+        format!("Failed to save credential store: {e}")
+    })
+}
+
+fn with_unlocked<T>(f: impl FnOnce(&mut UnlockedStore) -> Result<T, String>) -> Result<T, String> {
+    let mut store = STORE.write().map_err(to_string)?;
+    match store.as_mut() {
+        Some(store) => f(store),
+        None => Err("The credential store is locked".to_string()),
+    }
+}
+
+/// Looks up a secret in the store if it is unlocked, then in the secrets entered this session.
+pub fn get_secret(kind: CredentialKind, name: &str) -> Option<String> {
+    let key = (kind, name.to_lowercase());
+    let stored = STORE
+        .read()
+        .ok()
+        .and_then(|store| store.as_ref()?.entries.get(&key).map(|c| c.secret.clone()));
+    stored.or_else(|| SESSION_SECRETS.read().ok()?.get(&key).cloned())
+}
+
+/// Saves the secret in the store, or only for this session if the store is locked.
+pub fn remember_secret(kind: CredentialKind, name: &str, secret: &str) -> Result<(), String> {
+    if get_credential_store_status().unlocked {
+        return set_credential(kind, name, secret);
+    }
+    log::info!(
+        "Credential store is locked, keeping {kind:?} credential for {name} for this session"
+    );
+    SESSION_SECRETS
+        .write()
+        .map_err(to_string)?
+        .insert((kind, name.to_lowercase()), secret.trim().to_string());
+    Ok(())
+}
+
+pub fn forget_session_secret(kind: CredentialKind, name: &str) {
+    if let Ok(mut secrets) = SESSION_SECRETS.write() {
+        secrets.remove(&(kind, name.to_lowercase()));
+    }
+}
+
+/// The stored token for the host of a clone URL, e.g. "github.com" for
+/// "https://github.com/owner/repo.git".
+pub fn get_git_token(url: &str) -> Option<String> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_string();
+    get_secret(CredentialKind::GitToken, &host)
+}
+
+fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{tail}", "*".repeat(8))
+}
+
+#[tauri::command]
+pub fn get_credential_store_status() -> CredentialStoreStatus {
+    CredentialStoreStatus {
+        exists: get_store_path().exists(),
+        unlocked: STORE.read().map(|s| s.is_some()).unwrap_or(false),
+    }
+}
+
+/// Unlocks the store with `passphrase`, creating an empty store protected by it if there
+/// isn't one yet.
+#[tauri::command(rename_all = "snake_case")]
+pub fn unlock_credential_store(passphrase: &str) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+
+    let path = get_store_path();
+    let store = if path.exists() {
+        let content = std::fs::read_to_string(&path).map_err(to_string)?;
+        let encrypted: EncryptedStore = serde_json::from_str(&content).map_err(to_string)?;
+        if encrypted.version != STORE_VERSION {
+            return Err(format!(
+                "Unsupported credential store version {}",
+                encrypted.version
+            ));
+        }
+        let salt = BASE64.decode(&encrypted.salt).map_err(to_string)?;
+        let key = derive_key(passphrase, &salt)?;
+        let entries = decrypt(&encrypted, &key)?
+            .into_iter()
+            .map(|c| ((c.kind, c.name.clone()), c))
+            .collect();
+        UnlockedStore { key, salt, entries }
+    } else {
+        let mut salt = vec![0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let store = UnlockedStore {
+            key: derive_key(passphrase, &salt)?,
+            salt,
+            entries: BTreeMap::new(),
+        };
+        save(&store)?;
+        log::info!("Created credential store at {path:?}");
+        store
+    };
+
+    *STORE.write().map_err(to_string)? = Some(store);
+    log::info!("Unlocked credential store");
+
+    // Keys entered before unlocking are kept, rather than lost when the app closes
+    let session_secrets = std::mem::take(&mut *SESSION_SECRETS.write().map_err(to_string)?);
+    for ((kind, name), secret) in session_secrets {
+        set_credential(kind, &name, &secret)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn lock_credential_store() -> Result<(), String> {
+    *STORE.write().map_err(to_string)? = None;
+    log::info!("Locked credential store");
+    Ok(())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_credential(kind: CredentialKind, name: &str, secret: &str) -> Result<(), String> {
+    let name = name.trim().to_lowercase();
+    let secret = secret.trim();
+    if name.is_empty() || secret.is_empty() {
+        return Err("Credential name and secret must not be empty".to_string());
+    }
+
+    with_unlocked(|store| {
+        store.entries.insert(
+            (kind, name.clone()),
+            Credential {
+                kind,
+                name: name.clone(),
+                secret: secret.to_string(),
+                updated_at: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+        save(store)
+    })?;
+    log::info!("Saved {kind:?} credential for {name}");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_credentials() -> Result<Vec<CredentialInfo>, String> {
+    with_unlocked(|store| {
+        Ok(store
+            .entries
+            .values()
+            .map(|c| CredentialInfo {
+                kind: c.kind,
+                name: c.name.clone(),
+                masked: mask(&c.secret),
+                updated_at: c.updated_at.clone(),
+            })
+            .collect())
+    })
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn delete_credential(kind: CredentialKind, name: &str) -> Result<(), String> {
+    let name = name.trim().to_lowercase();
+    with_unlocked(|store| {
+        if store.entries.remove(&(kind, name.clone())).is_none() {
+            return Err(format!("No {kind:?} credential for {name}"));
+        }
+        save(store)
+    })?;
+    log::info!("Deleted {kind:?} credential for {name}");
+    Ok(())
+}
+
+/// Checks a secret against its service without storing it.
+pub async fn validate_secret(
+    kind: CredentialKind,
+    name: &str,
+    secret: &str,
+) -> Result<bool, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .connect_timeout(Duration::from_secs(10))
+        .user_agent("gitgauge")
+        .build()
+        .map_err(to_string)?;
+
+    let request = match (kind, name) {
+        (CredentialKind::LlmApiKey, GEMINI_CREDENTIAL) => client
+            .get("https://generativelanguage.googleapis.com/v1/models")
+            .query(&[("key", secret)]),
+        (CredentialKind::LlmApiKey, OPENAI_CREDENTIAL) => {
+            let base_url = match crate::llm::get_config() {
+                crate::llm::LlmConfig::OpenAiCompatible { base_url, .. } => base_url,
+                _ => String::from("https://api.openai.com/v1"),
+            };
+            client
+                .get(format!("{}/models", base_url.trim_end_matches('/')))
+                .bearer_auth(secret)
+        }
+        (CredentialKind::GitToken, host) => {
+            let Some((host_kind, api_url)) = git_hosts::host_api(&git_hosts::get_hosts(), host)
+            else {
+                return Err(format!("Can't validate tokens for {host}"));
+            };
+            // Bitbucket Data Center has no /user, but recent repos are per user
+            let path = match host_kind {
+                HostKind::Bitbucket if !api_url.starts_with("https://api.bitbucket.org") => {
+                    "/profile/recent/repos"
+                }
+                _ => "/user",
+            };
+            let request = client.get(format!("{api_url}{path}"));
+            git_hosts::authorise_api_request(request, host_kind, Some(secret))
+        }
+        _ => return Err(format!("Can't validate {kind:?} credentials for {name}")),
+    };
+
+    let response = request.send().await.map_err(to_string)?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::UNAUTHORIZED
+        | reqwest::StatusCode::FORBIDDEN
+        | reqwest::StatusCode::BAD_REQUEST => Ok(false),
+        status => {
+            let body = response.text().await.unwrap_or_default();
+            log::error!("Unexpected validation status {body}: {status}");
+            Err(format!("Unexpected status: {status}"))
+        }
+    }
+}
+
+/// Checks the stored secret against its service.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn validate_credential(kind: CredentialKind, name: &str) -> Result<bool, String> {
+    let name = name.trim().to_lowercase();
+    let secret = with_unlocked(|store| {
+        store
+            .entries
+            .get(&(kind, name.clone()))
+            .map(|c| c.secret.clone())
+            .ok_or_else(|| format!("No {kind:?} credential for {name}"))
+    })?;
+    let valid = validate_secret(kind, &name, &secret).await?;
+    log::info!("{kind:?} credential for {name} is valid: {valid}");
+    Ok(valid)
+}
@@ -0,0 +1,693 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::contributor::{
+    generate_initials, generate_profile_bg_colour, Contacts, Contributor, DateRange,
+};
+use crate::credentials::get_git_token;
+use crate::git_hosts::{self, authorise_api_request, HostKind, RemoteRepo};
+use crate::utils::to_string;
+
+const PER_PAGE: usize = 100;
+// Stops very active repositories from using up the hourly API rate limit
+const MAX_PAGES: usize = 10;
+// Reviews and comments are fetched per pull request, so only for the most recent ones
+const MAX_DETAILED_ITEMS: usize = 100;
+
+/// Collaboration on the host that git history doesn't show.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ForgeActivity {
+    pub pull_requests_opened: u64,
+    pub pull_requests_merged: u64,
+    /// Reviews (GitHub) or approvals (GitLab) of other people's pull requests.
+    pub reviews: u64,
+    /// Comments on the changed lines of a pull request.
+    pub review_comments: u64,
+    pub issues_opened: u64,
+    pub issues_closed: u64,
+    /// Comments in issue and pull request conversations.
+    pub discussion_comments: u64,
+}
+
+impl ForgeActivity {
+    pub fn add(&mut self, other: &ForgeActivity) {
+        self.pull_requests_opened += other.pull_requests_opened;
+        self.pull_requests_merged += other.pull_requests_merged;
+        self.reviews += other.reviews;
+        self.review_comments += other.review_comments;
+        self.issues_opened += other.issues_opened;
+        self.issues_closed += other.issues_closed;
+        self.discussion_comments += other.discussion_comments;
+    }
+}
+
+/// A user of the host and what they did there.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForgeUser {
+    pub login: String,
+    pub name: Option<String>,
+    /// Commit emails the host linked to this user.
+    pub emails: Vec<String>,
+    pub activity: ForgeActivity,
+}
+
+fn user<'a>(users: &'a mut HashMap<String, ForgeUser>, login: &str) -> &'a mut ForgeUser {
+    users.entry(login.to_string()).or_insert_with(|| ForgeUser {
+        login: login.to_string(),
+        name: None,
+        emails: Vec::new(),
+        activity: ForgeActivity::default(),
+    })
+}
+
+// The string at `path` in a JSON object, e.g. ["user", "login"]
+fn str_at<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(Value::as_str)
+}
+
+fn in_range(date_range: Option<&DateRange>, timestamp: Option<&str>) -> bool {
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    match (date_range, chrono::DateTime::parse_from_rfc3339(timestamp)) {
+        (None, _) => true,
+        (Some(range), Ok(time)) => range.contains(time.timestamp()),
+        (Some(_), Err(_)) => false,
+    }
+}
+
+// Whether an item last updated at `timestamp` can have activity in the range
+fn updated_in_range(date_range: Option<&DateRange>, timestamp: Option<&str>) -> bool {
+    let updated = timestamp.and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok());
+    match (date_range, updated) {
+        (Some(range), Some(updated)) => updated.timestamp() >= range.start,
+        _ => true,
+    }
+}
+
+/// Activity per user, and what couldn't be fetched.
+#[derive(Serialize, Debug)]
+pub struct ForgeActivityReport {
+    pub users: Vec<ForgeUser>,
+    /// Requests that failed or were skipped, so the counts are incomplete.
+    pub warnings: Vec<String>,
+}
+
+fn warn(warnings: &mut Vec<String>, what: &str, error: &str) {
+    log::warn!("Couldn't fetch {what}: {error}");
+    warnings.push(format!("Couldn't fetch {what}: {error}"));
+}
+
+// Keeps going without the items of a failed request, recording why
+fn or_warn<T: Default>(result: Result<T, String>, what: &str, warnings: &mut Vec<String>) -> T {
+    result.unwrap_or_else(|e| {
+        warn(warnings, what, &e);
+        T::default()
+    })
+}
+
+// Per item requests use up the rate limit for anonymous requests (60 an hour on GitHub)
+fn detail_requests_allowed(client: &ForgeClient, what: &str, warnings: &mut Vec<String>) -> bool {
+    if !client.has_token() {
+        warnings.push(format!(
+            "{what} are only counted with a token for {}",
+            client.host
+        ));
+    }
+    client.has_token()
+}
+
+fn to_rfc3339(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// Requests to a repository's host API.
+pub struct ForgeClient {
+    client: reqwest::Client,
+    kind: HostKind,
+    host: String,
+    api_url: String,
+    token: Option<String>,
+    pub remote: RemoteRepo,
+}
+
+impl ForgeClient {
+    /// A client for the host of the repository at `url`. `token` defaults to the stored token
+    /// for the host.
+    pub fn new(url: &str, token: Option<&str>) -> Result<Self, String> {
+        let remote = git_hosts::parse_remote_url(url, &git_hosts::get_hosts())?;
+        let api_url = remote
+            .api_url
+            .clone()
+            .ok_or_else(|| format!("{} has no known API", remote.host))?;
+        Ok(ForgeClient {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .connect_timeout(Duration::from_secs(10))
+                .user_agent("gitgauge")
+                .build()
+                .map_err(to_string)?,
+            kind: remote.kind,
+            host: remote.host.clone(),
+            api_url,
+            token: token
+                .map(String::from)
+                .or_else(|| get_git_token(&remote.clone_url)),
+            remote,
+        })
+    }
+
+    pub fn kind(&self) -> HostKind {
+        self.kind
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Value, String> {
+        let response = self.send(path, query).await?;
+        self.read(path, response).await
+    }
+
+    /// Like `get`, but None when the host has no such resource. Hosts also answer this way
+    /// for private repositories the request can't see.
+    pub async fn get_optional(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Option<Value>, String> {
+        let response = self.send(path, query).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        self.read(path, response).await.map(Some)
+    }
+
+    async fn send(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<reqwest::Response, String> {
+        let request = self
+            .client
+            .get(format!("{}{path}", self.api_url))
+            .query(query);
+        authorise_api_request(request, self.kind, self.token.as_deref())
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))
+    }
+
+    async fn read(&self, path: &str, response: reqwest::Response) -> Result<Value, String> {
+        match response.status() {
+            status if status.is_success() => response.json().await.map_err(to_string),
+            reqwest::StatusCode::UNAUTHORIZED => {
+                Err(format!("The token for {} was rejected", self.host))
+            }
+            reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::TOO_MANY_REQUESTS => Err(format!(
+                "{} refused the request, possibly because of its rate limit. Adding a token raises the limit.",
+                self.host
+            )),
+            reqwest::StatusCode::NOT_FOUND if self.token.is_none() => Err(format!(
+                "{path} was not found on {}. Private repositories need a token.",
+                self.host
+            )),
+            status => Err(format!("Unexpected status code {status} for {path}")),
+        }
+    }
+
+    // Every item of a paginated list, up to MAX_PAGES pages
+    async fn get_pages(&self, path: &str, query: &[(&str, String)]) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let mut page_query = query.to_vec();
+            page_query.push(("per_page", PER_PAGE.to_string()));
+            page_query.push(("page", page.to_string()));
+
+            let Value::Array(page_items) = self.get(path, &page_query).await? else {
+                return Err(format!("Expected a list from {path}"));
+            };
+            let last_page = page_items.len() < PER_PAGE;
+            items.extend(page_items);
+            if last_page {
+                return Ok(items);
+            }
+        }
+        log::warn!("Stopped reading {path} after {MAX_PAGES} pages");
+        Ok(items)
+    }
+}
+
+async fn fetch_github(
+    client: &ForgeClient,
+    owner: &str,
+    repo: &str,
+    date_range: Option<&DateRange>,
+    users: &mut HashMap<String, ForgeUser>,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    let base = format!("/repos/{owner}/{repo}");
+    // Only narrows the lists; each item is still checked against the range
+    let since: Vec<(&str, String)> = date_range
+        .map(|range| vec![("since", to_rfc3339(range.start))])
+        .unwrap_or_default();
+
+    // Commits carry both the login and the email, which links the two
+    for commit in client.get_pages(&format!("{base}/commits"), &since).await? {
+        let Some(login) = str_at(&commit, &["author", "login"]) else {
+            continue;
+        };
+        let user = user(users, login);
+        if let Some(email) = str_at(&commit, &["commit", "author", "email"]) {
+            let email = email.to_lowercase();
+            if !user.emails.contains(&email) {
+                user.emails.push(email);
+            }
+        }
+        if user.name.is_none() {
+            user.name = str_at(&commit, &["commit", "author", "name"]).map(String::from);
+        }
+    }
+
+    let pulls = client
+        .get_pages(&format!("{base}/pulls"), &[("state", "all".to_string())])
+        .await;
+    let pulls = or_warn(pulls, "pull requests", warnings);
+    let mut fetch_reviews = detail_requests_allowed(client, "Reviews", warnings);
+    for (index, pull) in pulls.iter().enumerate() {
+        let Some(author) = str_at(pull, &["user", "login"]) else {
+            continue;
+        };
+        if in_range(date_range, str_at(pull, &["created_at"])) {
+            user(users, author).activity.pull_requests_opened += 1;
+        }
+        if in_range(date_range, str_at(pull, &["merged_at"])) {
+            user(users, author).activity.pull_requests_merged += 1;
+        }
+
+        if !fetch_reviews
+            || index >= MAX_DETAILED_ITEMS
+            || !updated_in_range(date_range, str_at(pull, &["updated_at"]))
+        {
+            continue;
+        }
+        let Some(number) = pull.get("number").and_then(Value::as_u64) else {
+            continue;
+        };
+        let reviews = match client
+            .get_pages(&format!("{base}/pulls/{number}/reviews"), &[])
+            .await
+        {
+            Ok(reviews) => reviews,
+            // Most likely the rate limit, which the remaining pull requests would hit too
+            Err(e) => {
+                warn(warnings, "reviews", &e);
+                fetch_reviews = false;
+                continue;
+            }
+        };
+        for review in reviews {
+            let Some(reviewer) = str_at(&review, &["user", "login"]) else {
+                continue;
+            };
+            // Replies on your own pull request show up as reviews too
+            if reviewer != author
+                && str_at(&review, &["state"]) != Some("PENDING")
+                && in_range(date_range, str_at(&review, &["submitted_at"]))
+            {
+                user(users, reviewer).activity.reviews += 1;
+            }
+        }
+    }
+
+    let comments = client
+        .get_pages(&format!("{base}/pulls/comments"), &since)
+        .await;
+    for comment in or_warn(comments, "review comments", warnings) {
+        if let Some(login) = str_at(&comment, &["user", "login"]) {
+            if in_range(date_range, str_at(&comment, &["created_at"])) {
+                user(users, login).activity.review_comments += 1;
+            }
+        }
+    }
+
+    // The issues API includes pull requests, which are counted above
+    let mut issue_query = since.clone();
+    issue_query.push(("state", "all".to_string()));
+    let issues = client
+        .get_pages(&format!("{base}/issues"), &issue_query)
+        .await;
+    for issue in or_warn(issues, "issues", warnings) {
+        if issue.get("pull_request").is_some() {
+            continue;
+        }
+        if let Some(login) = str_at(&issue, &["user", "login"]) {
+            if in_range(date_range, str_at(&issue, &["created_at"])) {
+                user(users, login).activity.issues_opened += 1;
+            }
+        }
+    }
+
+    let events = client
+        .get_pages(&format!("{base}/issues/events"), &[])
+        .await;
+    for event in or_warn(events, "issue events", warnings) {
+        let is_pull = event
+            .get("issue")
+            .is_some_and(|issue| issue.get("pull_request").is_some());
+        if str_at(&event, &["event"]) != Some("closed") || is_pull {
+            continue;
+        }
+        if let Some(login) = str_at(&event, &["actor", "login"]) {
+            if in_range(date_range, str_at(&event, &["created_at"])) {
+                user(users, login).activity.issues_closed += 1;
+            }
+        }
+    }
+
+    let comments = client
+        .get_pages(&format!("{base}/issues/comments"), &since)
+        .await;
+    for comment in or_warn(comments, "discussion comments", warnings) {
+        if let Some(login) = str_at(&comment, &["user", "login"]) {
+            if in_range(date_range, str_at(&comment, &["created_at"])) {
+                user(users, login).activity.discussion_comments += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// GitLab users have a display name but commits don't link to them, so names are recorded for
+// matching against commit authors
+fn gitlab_user<'a>(
+    users: &'a mut HashMap<String, ForgeUser>,
+    author: &Value,
+) -> Option<&'a mut ForgeUser> {
+    let login = str_at(author, &["username"])?;
+    let user = user(users, login);
+    if user.name.is_none() {
+        user.name = str_at(author, &["name"]).map(String::from);
+    }
+    Some(user)
+}
+
+async fn count_gitlab_notes(
+    client: &ForgeClient,
+    path: &str,
+    date_range: Option<&DateRange>,
+    users: &mut HashMap<String, ForgeUser>,
+) -> Result<(), String> {
+    for note in client.get_pages(path, &[]).await? {
+        // System notes are events such as "changed the description"
+        if note.get("system").and_then(Value::as_bool) == Some(true)
+            || !in_range(date_range, str_at(&note, &["created_at"]))
+        {
+            continue;
+        }
+        let Some(author) = note.get("author") else {
+            continue;
+        };
+        let diff_note = str_at(&note, &["type"]) == Some("DiffNote");
+        if let Some(user) = gitlab_user(users, author) {
+            if diff_note {
+                user.activity.review_comments += 1;
+            } else {
+                user.activity.discussion_comments += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn fetch_gitlab(
+    client: &ForgeClient,
+    project_path: &str,
+    date_range: Option<&DateRange>,
+    users: &mut HashMap<String, ForgeUser>,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    let project: String = url::form_urlencoded::byte_serialize(project_path.as_bytes()).collect();
+    let base = format!("/projects/{project}");
+    let mut query = vec![("state", "all".to_string())];
+    if let Some(range) = date_range {
+        // Anything merged or closed in the range was updated in it
+        query.push(("updated_after", to_rfc3339(range.start)));
+    }
+
+    let merge_requests = client
+        .get_pages(&format!("{base}/merge_requests"), &query)
+        .await;
+    let merge_requests = or_warn(merge_requests, "merge requests", warnings);
+    let mut fetch_details = detail_requests_allowed(client, "Approvals and comments", warnings);
+    for (index, merge_request) in merge_requests.iter().enumerate() {
+        let Some(author) = merge_request.get("author") else {
+            continue;
+        };
+        let author_login = str_at(author, &["username"])
+            .unwrap_or_default()
+            .to_string();
+        if let Some(user) = gitlab_user(users, author) {
+            if in_range(date_range, str_at(merge_request, &["created_at"])) {
+                user.activity.pull_requests_opened += 1;
+            }
+            if in_range(date_range, str_at(merge_request, &["merged_at"])) {
+                user.activity.pull_requests_merged += 1;
+            }
+        }
+
+        if !fetch_details
+            || index >= MAX_DETAILED_ITEMS
+            || !updated_in_range(date_range, str_at(merge_request, &["updated_at"]))
+        {
+            continue;
+        }
+        let Some(iid) = merge_request.get("iid").and_then(Value::as_u64) else {
+            continue;
+        };
+        // Approvals have no date, so they count when the merge request is in the range
+        let approvals = if in_range(date_range, str_at(merge_request, &["updated_at"])) {
+            client
+                .get(&format!("{base}/merge_requests/{iid}/approvals"), &[])
+                .await
+        } else {
+            Ok(Value::Null)
+        };
+        let approvals = match approvals {
+            Ok(approvals) => approvals,
+            // Most likely the rate limit, which the remaining merge requests would hit too
+            Err(e) => {
+                warn(warnings, "approvals", &e);
+                fetch_details = false;
+                continue;
+            }
+        };
+        let approvers = approvals
+            .get("approved_by")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        for approval in approvers {
+            let Some(approver) = approval.get("user") else {
+                continue;
+            };
+            if str_at(approver, &["username"]) == Some(author_login.as_str()) {
+                continue;
+            }
+            if let Some(user) = gitlab_user(users, approver) {
+                user.activity.reviews += 1;
+            }
+        }
+        if let Err(e) = count_gitlab_notes(
+            client,
+            &format!("{base}/merge_requests/{iid}/notes"),
+            date_range,
+            users,
+        )
+        .await
+        {
+            warn(warnings, "merge request comments", &e);
+            fetch_details = false;
+        }
+    }
+
+    let issues = client.get_pages(&format!("{base}/issues"), &query).await;
+    let issues = or_warn(issues, "issues", warnings);
+    for (index, issue) in issues.iter().enumerate() {
+        if let Some(user) = issue.get("author").and_then(|a| gitlab_user(users, a)) {
+            if in_range(date_range, str_at(issue, &["created_at"])) {
+                user.activity.issues_opened += 1;
+            }
+        }
+        if let Some(user) = issue.get("closed_by").and_then(|a| gitlab_user(users, a)) {
+            if in_range(date_range, str_at(issue, &["closed_at"])) {
+                user.activity.issues_closed += 1;
+            }
+        }
+
+        if !fetch_details || index >= MAX_DETAILED_ITEMS {
+            continue;
+        }
+        if let Some(iid) = issue.get("iid").and_then(Value::as_u64) {
+            if let Err(e) = count_gitlab_notes(
+                client,
+                &format!("{base}/issues/{iid}/notes"),
+                date_range,
+                users,
+            )
+            .await
+            {
+                warn(warnings, "issue comments", &e);
+                fetch_details = false;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Pull requests, reviews, issues and comments per user of the repository at `url` on GitHub
+/// or GitLab. `token` defaults to the stored token for the host. Without one, or when the
+/// rate limit runs out, whatever could be fetched is returned with warnings.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_forge_activity(
+    url: &str,
+    date_range: Option<DateRange>,
+    token: Option<&str>,
+) -> Result<ForgeActivityReport, String> {
+    let client = ForgeClient::new(url, token)?;
+    let remote = &client.remote;
+
+    log::info!(
+        "Fetching {:?} activity for {}/{}",
+        remote.kind,
+        remote.owner,
+        remote.repo
+    );
+    let mut users = HashMap::new();
+    let mut warnings = Vec::new();
+    match remote.kind {
+        HostKind::GitHub => {
+            fetch_github(
+                &client,
+                &remote.owner,
+                &remote.repo,
+                date_range.as_ref(),
+                &mut users,
+                &mut warnings,
+            )
+            .await?
+        }
+        HostKind::GitLab => {
+            let project_path = format!("{}/{}", remote.owner, remote.repo);
+            fetch_gitlab(
+                &client,
+                &project_path,
+                date_range.as_ref(),
+                &mut users,
+                &mut warnings,
+            )
+            .await?
+        }
+        kind => return Err(format!("Activity isn't supported for {kind:?} hosts")),
+    }
+
+    let mut users: Vec<ForgeUser> = users
+        .into_values()
+        .filter(|u| u.activity != ForgeActivity::default() || !u.emails.is_empty())
+        .collect();
+    users.sort_by(|a, b| a.login.cmp(&b.login));
+    Ok(ForgeActivityReport { users, warnings })
+}
+
+// The contributor a host user made their commits as: an explicit mapping first, then a login
+// found by identity resolution, then a shared email, then a matching name. A mapping to an
+// unknown contributor falls through to the other matches.
+fn find_contributor(
+    contributors: &[Contributor],
+    user: &ForgeUser,
+    login_mapping: &HashMap<String, String>,
+) -> Option<usize> {
+    login_mapping
+        .get(&user.login)
+        .and_then(|username| contributors.iter().position(|c| &c.username == username))
+        .or_else(|| {
+            contributors
+                .iter()
+                .position(|c| c.logins.contains(&user.login))
+        })
+        .or_else(|| {
+            contributors.iter().position(|c| {
+                c.contacts
+                    .emails()
+                    .iter()
+                    .any(|email| user.emails.contains(&email.to_lowercase()))
+            })
+        })
+        .or_else(|| {
+            contributors.iter().position(|c| {
+                c.username.eq_ignore_ascii_case(&user.login)
+                    || user
+                        .name
+                        .as_ref()
+                        .is_some_and(|name| c.username.eq_ignore_ascii_case(name))
+            })
+        })
+}
+
+/// Adds each user's activity to the contributor they committed as. Users without commits, such
+/// as reviewers, are added as contributors with no commits. `login_mapping` maps host logins to
+/// contributor usernames for users that can't be matched by email or name.
+#[tauri::command(rename_all = "snake_case")]
+pub fn merge_forge_activity(
+    contributors: Vec<Contributor>,
+    users: Vec<ForgeUser>,
+    login_mapping: Option<HashMap<String, String>>,
+) -> Vec<Contributor> {
+    let login_mapping = login_mapping.unwrap_or_default();
+    let mut contributors = contributors;
+
+    for user in users {
+        match find_contributor(&contributors, &user, &login_mapping) {
+            Some(index) => {
+                let contributor = &mut contributors[index];
+                contributor
+                    .forge_activity
+                    .get_or_insert_with(ForgeActivity::default)
+                    .add(&user.activity);
+                if !contributor.logins.contains(&user.login) {
+                    contributor.logins.push(user.login);
+                }
+            }
+            None => {
+                log::info!("No commits found for {}", user.login);
+                contributors.push(Contributor {
+                    username: user.login.clone(),
+                    contacts: Contacts::EmailList(user.emails),
+                    total_commits: 0,
+                    additions: 0,
+                    deletions: 0,
+                    profile_colour: generate_profile_bg_colour(&user.login),
+                    username_initials: generate_initials(
+                        user.name.as_deref().unwrap_or(&user.login),
+                    ),
+                    total_regex_matches: 0,
+                    commits_matching_regex: 0,
+                    ai_summary: String::new(),
+                    forge_activity: Some(user.activity),
+                    logins: vec![user.login.clone()],
+                    names: user.name.into_iter().collect(),
+                });
+            }
+        }
+    }
+    contributors
+}
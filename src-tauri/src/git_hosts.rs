@@ -0,0 +1,1196 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use crate::settings::{read_settings, write_settings};
+
+/*
+The "git_hosts" section of settings.json lists self-hosted or extra hosts on top of the
+built-in ones below, e.g.
+[
+    { "hostname": "git.example.edu", "kind": "gitlab", "path_prefix": "gitlab" },
+    { "hostname": "*.visualstudio.com", "kind": "azure_devops" }
+]
+hostname may start with "*." to match any subdomain. path_prefix is the path the host is
+served under, if it isn't served from the root of the domain. api_base_url overrides where
+the host's REST API is, e.g. "https://github.example.com/api/v3".
+*/
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    /// github.com or GitHub Enterprise Server: /owner/repo
+    GitHub,
+    /// gitlab.com or self-managed GitLab: /group/subgroup/.../project
+    GitLab,
+    /// bitbucket.org: /workspace/repo, or Bitbucket Data Center: /scm/project/repo or
+    /// /projects/PROJECT/repos/repo
+    Bitbucket,
+    /// Gitea and Forgejo (e.g. codeberg.org): /owner/repo
+    Gitea,
+    /// dev.azure.com/org/project/_git/repo, org.visualstudio.com/project/_git/repo or Azure
+    /// DevOps Server: /collection/project/_git/repo
+    #[serde(rename = "azure_devops")]
+    AzureDevOps,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GitHost {
+    pub hostname: String,
+    pub kind: HostKind,
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GitHostInfo {
+    #[serde(flatten)]
+    pub host: GitHost,
+    pub builtin: bool,
+}
+
+/// A repository on a registered host.
+#[derive(Serialize, Debug, Clone)]
+pub struct RemoteRepo {
+    pub host: String,
+    pub kind: HostKind,
+    /// Owner, group path (GitLab), project key (Bitbucket Data Center) or
+    /// organisation/project (Azure DevOps).
+    pub owner: String,
+    pub repo: String,
+    /// The HTTPS URL to clone the repository from.
+    pub clone_url: String,
+    /// What a deep link such as /tree/feature-x or /pull/12 points at.
+    pub target: Option<LinkTarget>,
+    /// Base URL of the host's REST API, if it has one we know of.
+    pub api_url: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkTarget {
+    Branch {
+        name: String,
+    },
+    Commit {
+        sha: String,
+    },
+    /// A merge request, or pull request on hosts other than GitLab.
+    MergeRequest {
+        number: u64,
+    },
+}
+
+const BUILTIN_HOSTS: [(&str, HostKind); 9] = [
+    ("github.com", HostKind::GitHub),
+    ("www.github.com", HostKind::GitHub),
+    ("gitlab.com", HostKind::GitLab),
+    ("www.gitlab.com", HostKind::GitLab),
+    ("bitbucket.org", HostKind::Bitbucket),
+    ("codeberg.org", HostKind::Gitea),
+    ("gitea.com", HostKind::Gitea),
+    ("dev.azure.com", HostKind::AzureDevOps),
+    ("*.visualstudio.com", HostKind::AzureDevOps),
+];
+
+// GitHub username: alphanumeric or single hyphens, not at start/end.
+static GITHUB_OWNER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9-]*[a-zA-Z0-9])?$").unwrap());
+// Repository names on most hosts: alphanumeric, ., -, _
+static REPO_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap());
+// GitLab names: letters, digits, '_', '.', '-', '+'. Cannot start with '-' or end with '.'
+static GITLAB_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9_.+-]*[a-zA-Z0-9])?$").unwrap());
+// "git@host:owner/repo.git"
+static SCP_LIKE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:([^@/:]+)@)?([^@/:]+):(.+)$").unwrap());
+static COMMIT_SHA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[0-9a-fA-F]{7,40}$").unwrap());
+// Azure DevOps organisation, project and repository names may contain spaces
+static AZURE_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_. -]*[a-zA-Z0-9_-]$").unwrap());
+
+fn builtin_hosts() -> Vec<GitHost> {
+    BUILTIN_HOSTS
+        .iter()
+        .map(|(hostname, kind)| GitHost {
+            hostname: hostname.to_string(),
+            kind: *kind,
+            path_prefix: None,
+            api_base_url: None,
+        })
+        .collect()
+}
+
+/// User hosts first, so a self-hosted entry can override a built-in one.
+pub fn get_hosts() -> Vec<GitHost> {
+    let mut hosts = read_settings().git_hosts;
+    hosts.extend(builtin_hosts());
+    hosts
+}
+
+fn matches_hostname(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+/// The registered host for `host`, preferring the entry whose path prefix matches `path`.
+/// SSH paths don't include the prefix, so without a `path` the first entry for the host wins.
+pub fn find_host<'a>(hosts: &'a [GitHost], host: &str, path: Option<&str>) -> Option<&'a GitHost> {
+    let host = host.to_lowercase();
+    let mut candidates = hosts
+        .iter()
+        .filter(|h| matches_hostname(&h.hostname, &host));
+    let Some(path) = path else {
+        return candidates.next();
+    };
+    let path = path.trim_start_matches('/');
+    candidates.find(|h| match h.path_prefix.as_deref() {
+        Some(prefix) => path == prefix || path.starts_with(&format!("{prefix}/")),
+        None => true,
+    })
+}
+
+fn base_url(scheme: &str, host: &str, path_prefix: Option<&str>) -> String {
+    match path_prefix {
+        Some(prefix) => format!("{scheme}://{host}/{prefix}"),
+        None => format!("{scheme}://{host}"),
+    }
+}
+
+/// Adds `token` to a request to the host's API the way the host expects it.
+pub fn authorise_api_request(
+    request: reqwest::RequestBuilder,
+    kind: HostKind,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    match (kind, token) {
+        (_, None) => request,
+        (HostKind::GitLab, Some(token)) => request.header("PRIVATE-TOKEN", token),
+        (_, Some(token)) => request.bearer_auth(token),
+    }
+}
+
+fn default_api_url(kind: HostKind, host: &str, base: &str) -> Option<String> {
+    match kind {
+        HostKind::GitHub if host == "github.com" || host == "www.github.com" => {
+            Some(String::from("https://api.github.com"))
+        }
+        HostKind::GitHub => Some(format!("{base}/api/v3")),
+        HostKind::GitLab => Some(format!("{base}/api/v4")),
+        HostKind::Bitbucket if host == "bitbucket.org" => {
+            Some(String::from("https://api.bitbucket.org/2.0"))
+        }
+        HostKind::Bitbucket => Some(format!("{base}/rest/api/1.0")),
+        HostKind::Gitea => Some(format!("{base}/api/v1")),
+        HostKind::AzureDevOps => None,
+    }
+}
+
+/// The kind and API URL of the registered host `host`, as tokens are named, e.g. "github.com"
+/// or "git.uni.edu". Hosts registered with a port also match their bare hostname.
+pub fn host_api(hosts: &[GitHost], host: &str) -> Option<(HostKind, String)> {
+    let host = host.to_lowercase();
+    let entry = find_host(hosts, &host, None).or_else(|| {
+        hosts
+            .iter()
+            .find(|h| h.hostname.split(':').next() == Some(host.as_str()))
+    })?;
+    let host = if entry.hostname.contains(':') {
+        entry.hostname.to_lowercase()
+    } else {
+        host
+    };
+    let base = base_url("https", &host, entry.path_prefix.as_deref());
+    let api_url = entry
+        .api_base_url
+        .clone()
+        .or_else(|| default_api_url(entry.kind, &host, &base))?;
+    Some((entry.kind, api_url.trim_end_matches('/').to_string()))
+}
+
+fn check_repo_name(repo: &str, host_name: &str) -> Result<(), String> {
+    if !REPO_NAME.is_match(repo)
+        || repo.starts_with('-')
+        || repo.ends_with('-')
+        || repo.ends_with('.')
+        || repo.contains("--")
+    {
+        return Err(format!(
+            "Invalid {host_name} repository name: '{repo}'. Contains invalid characters or patterns."
+        ));
+    }
+    Ok(())
+}
+
+fn parse_github(host: &str, base: &str, segments: &[String]) -> Result<RemoteRepo, String> {
+    if segments.len() < 2 {
+        return Err(String::from(
+            "GitHub URL must contain at least an owner and a repository name.",
+        ));
+    }
+    let owner = segments[0].clone();
+    let repo = segments[1].trim_end_matches(".git").to_string();
+
+    if !GITHUB_OWNER.is_match(&owner) || owner.contains("--") {
+        return Err(format!(
+            "Invalid GitHub owner name: '{owner}'. Must be alphanumeric or single hyphens, not starting/ending with a hyphen, and no consecutive hyphens."
+        ));
+    }
+    check_repo_name(&repo, "GitHub")?;
+
+    Ok(RemoteRepo {
+        host: host.to_string(),
+        kind: HostKind::GitHub,
+        target: None,
+        api_url: None,
+        clone_url: format!("{base}/{owner}/{repo}.git"),
+        owner,
+        repo,
+    })
+}
+
+fn parse_gitlab(host: &str, base: &str, segments: &[String]) -> Result<RemoteRepo, String> {
+    let mut segments = segments.to_vec();
+    if segments.is_empty() {
+        return Err(String::from("GitLab URL path is empty."));
+    }
+
+    let repo = segments
+        .pop()
+        .unwrap_or_default()
+        .trim_end_matches(".git")
+        .to_string();
+    if segments.is_empty() {
+        return Err(String::from(
+            "GitLab URL must contain at least a group/owner and a project name.",
+        ));
+    }
+    let owner = segments.join("/");
+
+    for segment in &segments {
+        if !GITLAB_NAME.is_match(segment) || segment.contains("--") {
+            return Err(format!("Invalid GitLab group/owner segment: '{segment}'."));
+        }
+    }
+    if !GITLAB_NAME.is_match(&repo) || repo.ends_with('.') || repo.contains("--") {
+        return Err(format!("Invalid GitLab project name: '{repo}'."));
+    }
+
+    Ok(RemoteRepo {
+        host: host.to_string(),
+        kind: HostKind::GitLab,
+        target: None,
+        api_url: None,
+        clone_url: format!("{base}/{owner}/{repo}.git"),
+        owner,
+        repo,
+    })
+}
+
+fn parse_bitbucket(host: &str, base: &str, segments: &[String]) -> Result<RemoteRepo, String> {
+    // Bitbucket Data Center uses /scm/KEY/repo.git to clone and /projects/KEY/repos/repo to browse
+    let (owner, repo, data_center) = match segments {
+        [scm, key, repo, ..] if scm == "scm" => (key.clone(), repo.clone(), true),
+        [projects, key, repos, repo, ..] if projects == "projects" && repos == "repos" => {
+            (key.clone(), repo.clone(), true)
+        }
+        [workspace, repo, ..] => (workspace.clone(), repo.clone(), false),
+        _ => {
+            return Err(String::from(
+                "Bitbucket URL must contain at least a workspace and a repository name.",
+            ))
+        }
+    };
+    let repo = repo.trim_end_matches(".git").to_string();
+
+    if !REPO_NAME.is_match(&owner) {
+        return Err(format!(
+            "Invalid Bitbucket workspace or project: '{owner}'."
+        ));
+    }
+    check_repo_name(&repo, "Bitbucket")?;
+
+    let clone_url = if data_center {
+        format!("{base}/scm/{}/{repo}.git", owner.to_lowercase())
+    } else {
+        format!("{base}/{owner}/{repo}.git")
+    };
+    Ok(RemoteRepo {
+        host: host.to_string(),
+        kind: HostKind::Bitbucket,
+        target: None,
+        api_url: None,
+        owner,
+        repo,
+        clone_url,
+    })
+}
+
+fn parse_gitea(host: &str, base: &str, segments: &[String]) -> Result<RemoteRepo, String> {
+    if segments.len() < 2 {
+        return Err(String::from(
+            "Gitea/Forgejo URL must contain at least an owner and a repository name.",
+        ));
+    }
+    let owner = segments[0].clone();
+    let repo = segments[1].trim_end_matches(".git").to_string();
+
+    if !REPO_NAME.is_match(&owner) || owner.starts_with(['-', '.']) {
+        return Err(format!("Invalid Gitea/Forgejo owner name: '{owner}'."));
+    }
+    check_repo_name(&repo, "Gitea/Forgejo")?;
+
+    Ok(RemoteRepo {
+        host: host.to_string(),
+        kind: HostKind::Gitea,
+        target: None,
+        api_url: None,
+        clone_url: format!("{base}/{owner}/{repo}.git"),
+        owner,
+        repo,
+    })
+}
+
+fn parse_azure(host: &str, base: &str, segments: &[String]) -> Result<RemoteRepo, String> {
+    let Some(git_index) = segments.iter().position(|s| s == "_git") else {
+        return Err(String::from(
+            "Azure DevOps URL must be a repository URL containing '/_git/'.",
+        ));
+    };
+    let repo = segments
+        .get(git_index + 1)
+        .map(|r| r.trim_end_matches(".git").to_string())
+        .ok_or_else(|| String::from("Azure DevOps URL is missing the repository name."))?;
+    let before = &segments[..git_index];
+
+    // org.visualstudio.com/project/_git/repo has the organisation in the hostname, and a
+    // repository named after its project may leave the project out (org/_git/repo). Older
+    // visualstudio.com URLs also name the DefaultCollection, which isn't part of the owner.
+    let owner_parts: Vec<String> = if host.ends_with(".visualstudio.com") {
+        let org = host.trim_end_matches(".visualstudio.com").to_string();
+        std::iter::once(org)
+            .chain(
+                before
+                    .iter()
+                    .filter(|s| !s.eq_ignore_ascii_case("DefaultCollection"))
+                    .cloned(),
+            )
+            .collect()
+    } else {
+        before.to_vec()
+    };
+    if owner_parts.is_empty() {
+        return Err(String::from(
+            "Azure DevOps URL must contain an organisation and a project.",
+        ));
+    }
+    for part in owner_parts.iter().chain(std::iter::once(&repo)) {
+        if !AZURE_NAME.is_match(part) {
+            return Err(format!("Invalid Azure DevOps name: '{part}'."));
+        }
+    }
+
+    let path: Vec<String> = before
+        .iter()
+        .chain(["_git".to_string(), repo.clone()].iter())
+        .map(|s| s.replace(' ', "%20"))
+        .collect();
+    Ok(RemoteRepo {
+        host: host.to_string(),
+        kind: HostKind::AzureDevOps,
+        target: None,
+        api_url: None,
+        owner: owner_parts.join("/"),
+        clone_url: format!("{base}/{}", path.join("/")),
+        repo,
+    })
+}
+
+// How many leading segments name the repository; the rest are a deep link into it
+fn repo_segment_count(kind: HostKind, segments: &[String]) -> usize {
+    let count = match kind {
+        HostKind::GitHub | HostKind::Gitea => 2,
+        // Everything after "/-/" is a page of the project
+        HostKind::GitLab => segments
+            .iter()
+            .position(|s| s == "-")
+            .unwrap_or(segments.len()),
+        HostKind::Bitbucket => match segments.first().map(String::as_str) {
+            Some("scm") => 3,
+            Some("projects") => 4,
+            _ => 2,
+        },
+        HostKind::AzureDevOps => segments
+            .iter()
+            .position(|s| s == "_git")
+            .map_or(segments.len(), |i| i + 2),
+    };
+    count.min(segments.len())
+}
+
+fn commit_target(sha: Option<&String>) -> Option<LinkTarget> {
+    sha.filter(|sha| COMMIT_SHA.is_match(sha))
+        .map(|sha| LinkTarget::Commit {
+            sha: sha.to_lowercase(),
+        })
+}
+
+fn merge_request_target(number: Option<&String>) -> Option<LinkTarget> {
+    number
+        .and_then(|n| n.parse().ok())
+        .map(|number| LinkTarget::MergeRequest { number })
+}
+
+fn branch_target(parts: &[String]) -> Option<LinkTarget> {
+    (!parts.is_empty()).then(|| LinkTarget::Branch {
+        name: parts.join("/"),
+    })
+}
+
+// Branch names can contain slashes, so /tree/feature/x is the branch "feature/x". A link to a
+// directory in a branch (/tree/main/src) can't be told apart and is read the same way.
+fn link_target(kind: HostKind, rest: &[String], query: &[(String, String)]) -> Option<LinkTarget> {
+    let query_value = |key: &str| {
+        query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    // GitLab pages follow a "-" segment
+    let rest = match rest.split_first() {
+        Some((dash, pages)) if kind == HostKind::GitLab && dash == "-" => pages,
+        _ => rest,
+    };
+    let page = rest.first().map(String::as_str);
+    match kind {
+        HostKind::GitHub | HostKind::GitLab => match page {
+            Some("tree") | Some("commits") => branch_target(&rest[1..]),
+            Some("commit") => commit_target(rest.get(1)),
+            Some("pull") | Some("merge_requests") => merge_request_target(rest.get(1)),
+            _ => None,
+        },
+        HostKind::Bitbucket => match page {
+            Some("branch") => branch_target(&rest[1..]),
+            Some("commits") => commit_target(rest.get(1)),
+            Some("pull-requests") => merge_request_target(rest.get(1)),
+            // Bitbucket Cloud /src/<ref>/path, where the ref is a branch or a full hash
+            Some("src") => rest.get(1).and_then(|r| {
+                if r.len() == 40 {
+                    commit_target(Some(r))
+                } else {
+                    branch_target(std::slice::from_ref(r))
+                }
+            }),
+            // Bitbucket Data Center /browse?at=refs/heads/<branch>
+            _ => query_value("at").and_then(|at| {
+                at.strip_prefix("refs/heads/")
+                    .map(|name| LinkTarget::Branch {
+                        name: name.to_string(),
+                    })
+            }),
+        },
+        HostKind::Gitea => match (page, rest.get(1).map(String::as_str)) {
+            (Some("src") | Some("commits"), Some("branch")) => branch_target(&rest[2..]),
+            (Some("src") | Some("commits"), Some("commit")) => commit_target(rest.get(2)),
+            (Some("commit"), _) => commit_target(rest.get(1)),
+            (Some("pulls"), _) => merge_request_target(rest.get(1)),
+            _ => None,
+        },
+        HostKind::AzureDevOps => match page {
+            Some("commit") => commit_target(rest.get(1)),
+            Some("pullrequest") => merge_request_target(rest.get(1)),
+            // ?version=GB<branch> or ?version=GC<commit>
+            _ => query_value("version").and_then(|version| {
+                if let Some(name) = version.strip_prefix("GB") {
+                    Some(LinkTarget::Branch {
+                        name: name.to_string(),
+                    })
+                } else {
+                    commit_target(version.strip_prefix("GC").map(String::from).as_ref())
+                }
+            }),
+        },
+    }
+}
+
+// Turns scp-like "git@host:owner/repo.git" into "ssh://git@host/owner/repo.git"
+fn normalise_url(url_str: &str) -> String {
+    let url_str = url_str.trim();
+    if url_str.contains("://") {
+        return url_str.to_string();
+    }
+    match SCP_LIKE.captures(url_str) {
+        Some(caps) => {
+            let user = caps
+                .get(1)
+                .map_or(String::new(), |u| format!("{}@", u.as_str()));
+            format!(
+                "ssh://{user}{}/{}",
+                &caps[2],
+                caps[3].trim_start_matches('/')
+            )
+        }
+        None => url_str.to_string(),
+    }
+}
+
+// Azure DevOps SSH URLs have their own hosts and shape:
+// ssh.dev.azure.com:v3/org/project/repo and vs-ssh.visualstudio.com:v3/org/project/repo
+fn azure_ssh_path(host: &str, segments: &[String]) -> Option<(String, Vec<String>)> {
+    let [v3, org, project, repo] = segments else {
+        return None;
+    };
+    if v3 != "v3" {
+        return None;
+    }
+    let git_path = vec![project.clone(), "_git".to_string(), repo.clone()];
+    match host {
+        "ssh.dev.azure.com" => Some((
+            "dev.azure.com".to_string(),
+            std::iter::once(org.clone()).chain(git_path).collect(),
+        )),
+        "vs-ssh.visualstudio.com" => Some((format!("{org}.visualstudio.com"), git_path)),
+        _ => None,
+    }
+}
+
+/// Parses a web, HTTPS, SSH or scp-like ("git@host:owner/repo.git") URL of a repository on a
+/// registered host. Deep links to a branch, commit or merge request also give their target.
+pub fn parse_remote_url(url_str: &str, hosts: &[GitHost]) -> Result<RemoteRepo, String> {
+    let url = url::Url::parse(&normalise_url(url_str)).map_err(|e| format!("Invalid URL: {e}"))?;
+    let ssh = matches!(url.scheme(), "ssh" | "git+ssh");
+    if !ssh && !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme '{}'.", url.scheme()));
+    }
+
+    let mut host = url.host_str().unwrap_or_default().to_lowercase();
+    let mut segments: Vec<String> = url.path_segments().map_or_else(Vec::new, |segments| {
+        segments
+            .filter(|s| !s.is_empty())
+            .map(decode_spaces)
+            .collect()
+    });
+    if ssh {
+        if let Some((web_host, path)) = azure_ssh_path(&host, &segments) {
+            host = web_host;
+            segments = path;
+        }
+    }
+
+    // Web URLs keep their scheme and port; SSH ports aren't the web server's, so SSH remotes
+    // fall back to HTTPS on the default port.
+    let (scheme, host_port) = match (ssh, url.port()) {
+        (true, _) => ("https", host.clone()),
+        (false, Some(port)) => (url.scheme(), format!("{host}:{port}")),
+        (false, None) => (url.scheme(), host.clone()),
+    };
+
+    let path = (!ssh).then(|| url.path());
+    let entry = find_host(hosts, &host_port, path)
+        .or_else(|| find_host(hosts, &host, path))
+        .ok_or_else(|| {
+            format!("'{host_port}' is not a known git host. Add it to the git hosts in settings.")
+        })?;
+    let host = host_port;
+    if let (Some(prefix), false) = (entry.path_prefix.as_deref(), ssh) {
+        segments.drain(..prefix.split('/').count().min(segments.len()));
+    }
+
+    let repo_count = repo_segment_count(entry.kind, &segments);
+    let (repo_segments, rest) = segments.split_at(repo_count);
+    let base = base_url(scheme, &host, entry.path_prefix.as_deref());
+    let mut remote = match entry.kind {
+        HostKind::GitHub => parse_github(&host, &base, repo_segments),
+        HostKind::GitLab => parse_gitlab(&host, &base, repo_segments),
+        HostKind::Bitbucket => parse_bitbucket(&host, &base, repo_segments),
+        HostKind::Gitea => parse_gitea(&host, &base, repo_segments),
+        HostKind::AzureDevOps => parse_azure(&host, &base, repo_segments),
+    }?;
+
+    let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    remote.target = link_target(entry.kind, rest, &query);
+    remote.api_url = entry
+        .api_base_url
+        .clone()
+        .or_else(|| default_api_url(entry.kind, &host, &base))
+        .map(|url| url.trim_end_matches('/').to_string());
+    Ok(remote)
+}
+
+// Azure DevOps names can contain spaces, which appear as %20 in URLs
+fn decode_spaces(segment: &str) -> String {
+    segment.replace("%20", " ")
+}
+
+fn validate_host(host: &GitHost) -> Result<(), String> {
+    let hostname = host.hostname.strip_prefix("*.").unwrap_or(&host.hostname);
+    if hostname.is_empty()
+        || !hostname
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == ':')
+    {
+        return Err(format!(
+            "Invalid hostname '{}'. Use just the hostname, e.g. git.example.edu",
+            host.hostname
+        ));
+    }
+    if let Some(ref prefix) = host.path_prefix {
+        if prefix.is_empty() || prefix.contains("//") {
+            return Err(format!("Invalid path prefix '{prefix}' for {hostname}"));
+        }
+    }
+    if let Some(ref api_base_url) = host.api_base_url {
+        match url::Url::parse(api_base_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => {
+                return Err(format!(
+                    "Invalid API base URL '{api_base_url}' for {hostname}"
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_git_hosts() -> Vec<GitHostInfo> {
+    let custom = read_settings()
+        .git_hosts
+        .into_iter()
+        .map(|host| GitHostInfo {
+            host,
+            builtin: false,
+        });
+    let builtin = builtin_hosts().into_iter().map(|host| GitHostInfo {
+        host,
+        builtin: true,
+    });
+    custom.chain(builtin).collect()
+}
+
+/// Replaces the user's git hosts. Built-in hosts are always available.
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_git_hosts(hosts: Vec<GitHost>) -> Result<(), String> {
+    let hosts: Vec<GitHost> = hosts
+        .into_iter()
+        .map(|host| GitHost {
+            hostname: host.hostname.trim().to_lowercase(),
+            path_prefix: host
+                .path_prefix
+                .map(|p| p.trim().trim_matches('/').to_string())
+                .filter(|p| !p.is_empty()),
+            api_base_url: host
+                .api_base_url
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty()),
+            ..host
+        })
+        .collect();
+    for host in &hosts {
+        validate_host(host)?;
+    }
+
+    let mut settings = read_settings();
+    settings.git_hosts = hosts;
+    write_settings(&settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts() -> Vec<GitHost> {
+        let mut hosts = vec![
+            GitHost {
+                hostname: "git.uni.edu".to_string(),
+                kind: HostKind::GitLab,
+                path_prefix: Some("gitlab".to_string()),
+                api_base_url: None,
+            },
+            GitHost {
+                hostname: "stash.corp.com".to_string(),
+                kind: HostKind::Bitbucket,
+                path_prefix: None,
+                api_base_url: Some("https://stash.corp.com/rest/api/1.0".to_string()),
+            },
+            GitHost {
+                hostname: "git.uni.edu:8443".to_string(),
+                kind: HostKind::Gitea,
+                path_prefix: None,
+                api_base_url: None,
+            },
+            GitHost {
+                hostname: "gitlab.lab.local".to_string(),
+                kind: HostKind::GitLab,
+                path_prefix: None,
+                api_base_url: None,
+            },
+        ];
+        hosts.extend(builtin_hosts());
+        hosts
+    }
+
+    fn branch(name: &str) -> Option<LinkTarget> {
+        Some(LinkTarget::Branch {
+            name: name.to_string(),
+        })
+    }
+
+    fn commit(sha: &str) -> Option<LinkTarget> {
+        Some(LinkTarget::Commit {
+            sha: sha.to_string(),
+        })
+    }
+
+    fn merge_request(number: u64) -> Option<LinkTarget> {
+        Some(LinkTarget::MergeRequest { number })
+    }
+
+    #[test]
+    fn matches_hostname_with_wildcards() {
+        let cases = [
+            ("github.com", "github.com", true),
+            ("GitHub.com", "github.com", true),
+            ("github.com", "gist.github.com", false),
+            ("*.visualstudio.com", "org.visualstudio.com", true),
+            ("*.visualstudio.com", "a.b.visualstudio.com", true),
+            ("*.visualstudio.com", "visualstudio.com", false),
+            ("*.visualstudio.com", ".visualstudio.com", false),
+            ("*.visualstudio.com", "orgvisualstudio.com", false),
+        ];
+        for (pattern, host, expected) in cases {
+            assert_eq!(
+                matches_hostname(pattern, host),
+                expected,
+                "{pattern} / {host}"
+            );
+        }
+    }
+
+    #[test]
+    fn find_host_respects_path_prefix() {
+        let hosts = hosts();
+        let cases = [
+            (
+                "git.uni.edu",
+                Some("/gitlab/team/proj"),
+                Some("git.uni.edu"),
+            ),
+            ("git.uni.edu", Some("gitlab"), Some("git.uni.edu")),
+            ("git.uni.edu", Some("/gitlabx/team/proj"), None),
+            ("git.uni.edu", None, Some("git.uni.edu")),
+            ("git.uni.edu:8443", None, Some("git.uni.edu:8443")),
+            ("GITHUB.COM", Some("/o/r"), Some("github.com")),
+            ("org.visualstudio.com", None, Some("*.visualstudio.com")),
+            ("example.com", None, None),
+        ];
+        for (host, path, expected) in cases {
+            assert_eq!(
+                find_host(&hosts, host, path).map(|h| h.hostname.as_str()),
+                expected,
+                "{host} {path:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_remote_url_shapes() {
+        use HostKind::*;
+        let cases = [
+            // HTTPS
+            (
+                "https://github.com/rust-lang/rust.git",
+                GitHub,
+                "rust-lang",
+                "rust",
+                "https://github.com/rust-lang/rust.git",
+                None,
+            ),
+            (
+                "http://www.github.com/o/r",
+                GitHub,
+                "o",
+                "r",
+                "http://www.github.com/o/r.git",
+                None,
+            ),
+            (
+                "https://gitlab.com/a/b/c",
+                GitLab,
+                "a/b",
+                "c",
+                "https://gitlab.com/a/b/c.git",
+                None,
+            ),
+            (
+                "https://git.uni.edu/gitlab/team/proj.git",
+                GitLab,
+                "team",
+                "proj",
+                "https://git.uni.edu/gitlab/team/proj.git",
+                None,
+            ),
+            (
+                "https://codeberg.org/forgejo/forgejo",
+                Gitea,
+                "forgejo",
+                "forgejo",
+                "https://codeberg.org/forgejo/forgejo.git",
+                None,
+            ),
+            // Self-hosted on another port or over plain HTTP
+            (
+                "https://git.uni.edu:8443/team/proj",
+                Gitea,
+                "team",
+                "proj",
+                "https://git.uni.edu:8443/team/proj.git",
+                None,
+            ),
+            (
+                "http://gitlab.lab.local/group/proj.git",
+                GitLab,
+                "group",
+                "proj",
+                "http://gitlab.lab.local/group/proj.git",
+                None,
+            ),
+            (
+                "http://gitlab.lab.local:8080/group/proj",
+                GitLab,
+                "group",
+                "proj",
+                "http://gitlab.lab.local:8080/group/proj.git",
+                None,
+            ),
+            (
+                "git@gitlab.lab.local:group/proj.git",
+                GitLab,
+                "group",
+                "proj",
+                "https://gitlab.lab.local/group/proj.git",
+                None,
+            ),
+            // Bitbucket Cloud and Data Center
+            (
+                "https://bitbucket.org/ws/repo",
+                Bitbucket,
+                "ws",
+                "repo",
+                "https://bitbucket.org/ws/repo.git",
+                None,
+            ),
+            (
+                "https://stash.corp.com/projects/ABC/repos/thing/browse",
+                Bitbucket,
+                "ABC",
+                "thing",
+                "https://stash.corp.com/scm/abc/thing.git",
+                None,
+            ),
+            // Azure DevOps
+            (
+                "https://dev.azure.com/org/My%20Project/_git/repo",
+                AzureDevOps,
+                "org/My Project",
+                "repo",
+                "https://dev.azure.com/org/My%20Project/_git/repo",
+                None,
+            ),
+            (
+                "https://dev.azure.com/org/_git/repo",
+                AzureDevOps,
+                "org",
+                "repo",
+                "https://dev.azure.com/org/_git/repo",
+                None,
+            ),
+            (
+                "https://org.visualstudio.com/proj/_git/repo",
+                AzureDevOps,
+                "org/proj",
+                "repo",
+                "https://org.visualstudio.com/proj/_git/repo",
+                None,
+            ),
+            (
+                "https://org.visualstudio.com/DefaultCollection/proj/_git/repo",
+                AzureDevOps,
+                "org/proj",
+                "repo",
+                "https://org.visualstudio.com/DefaultCollection/proj/_git/repo",
+                None,
+            ),
+            // SSH and scp-style
+            (
+                "git@github.com:org/repo.git",
+                GitHub,
+                "org",
+                "repo",
+                "https://github.com/org/repo.git",
+                None,
+            ),
+            (
+                "ssh://git@gitlab.com:2222/g/sub/p.git",
+                GitLab,
+                "g/sub",
+                "p",
+                "https://gitlab.com/g/sub/p.git",
+                None,
+            ),
+            (
+                "git@bitbucket.org:w/r.git",
+                Bitbucket,
+                "w",
+                "r",
+                "https://bitbucket.org/w/r.git",
+                None,
+            ),
+            (
+                "git@git.uni.edu:team/proj.git",
+                GitLab,
+                "team",
+                "proj",
+                "https://git.uni.edu/gitlab/team/proj.git",
+                None,
+            ),
+            (
+                "git@ssh.dev.azure.com:v3/org/proj/repo",
+                AzureDevOps,
+                "org/proj",
+                "repo",
+                "https://dev.azure.com/org/proj/_git/repo",
+                None,
+            ),
+            // Deep links
+            (
+                "https://github.com/o/r/tree/feature/x",
+                GitHub,
+                "o",
+                "r",
+                "https://github.com/o/r.git",
+                branch("feature/x"),
+            ),
+            (
+                "https://github.com/o/r/tree/main?x=1#readme",
+                GitHub,
+                "o",
+                "r",
+                "https://github.com/o/r.git",
+                branch("main"),
+            ),
+            (
+                "https://github.com/o/r/commit/abcdef1234",
+                GitHub,
+                "o",
+                "r",
+                "https://github.com/o/r.git",
+                commit("abcdef1234"),
+            ),
+            (
+                "https://github.com/o/r/pull/7",
+                GitHub,
+                "o",
+                "r",
+                "https://github.com/o/r.git",
+                merge_request(7),
+            ),
+            (
+                "https://gitlab.com/g/p/-/merge_requests/12",
+                GitLab,
+                "g",
+                "p",
+                "https://gitlab.com/g/p.git",
+                merge_request(12),
+            ),
+            (
+                "https://gitlab.com/g/p/-/tree/main?ref_type=heads",
+                GitLab,
+                "g",
+                "p",
+                "https://gitlab.com/g/p.git",
+                branch("main"),
+            ),
+            (
+                "https://gitlab.com/g/p/-/commit/0123abc",
+                GitLab,
+                "g",
+                "p",
+                "https://gitlab.com/g/p.git",
+                commit("0123abc"),
+            ),
+            (
+                "https://bitbucket.org/ws/repo/src/main/",
+                Bitbucket,
+                "ws",
+                "repo",
+                "https://bitbucket.org/ws/repo.git",
+                branch("main"),
+            ),
+            (
+                "https://bitbucket.org/w/r/pull-requests/3",
+                Bitbucket,
+                "w",
+                "r",
+                "https://bitbucket.org/w/r.git",
+                merge_request(3),
+            ),
+            (
+                "https://bitbucket.org/w/r/commits/abcdef12",
+                Bitbucket,
+                "w",
+                "r",
+                "https://bitbucket.org/w/r.git",
+                commit("abcdef12"),
+            ),
+            (
+                "https://codeberg.org/a/b/src/branch/dev",
+                Gitea,
+                "a",
+                "b",
+                "https://codeberg.org/a/b.git",
+                branch("dev"),
+            ),
+            (
+                "https://gitea.com/o/r/pulls/4",
+                Gitea,
+                "o",
+                "r",
+                "https://gitea.com/o/r.git",
+                merge_request(4),
+            ),
+            (
+                "https://dev.azure.com/o/p/_git/r?version=GBmain",
+                AzureDevOps,
+                "o/p",
+                "r",
+                "https://dev.azure.com/o/p/_git/r",
+                branch("main"),
+            ),
+            (
+                "https://dev.azure.com/o/p/_git/r?version=GCabcdef1",
+                AzureDevOps,
+                "o/p",
+                "r",
+                "https://dev.azure.com/o/p/_git/r",
+                commit("abcdef1"),
+            ),
+            (
+                "https://dev.azure.com/o/p/_git/r/pullrequest/9",
+                AzureDevOps,
+                "o/p",
+                "r",
+                "https://dev.azure.com/o/p/_git/r",
+                merge_request(9),
+            ),
+        ];
+
+        let hosts = hosts();
+        for (url, kind, owner, repo, clone_url, target) in cases {
+            let remote = parse_remote_url(url, &hosts)
+                .unwrap_or_else(|e| panic!("{url} failed to parse: {e}"));
+            assert_eq!(remote.kind, kind, "{url}");
+            assert_eq!(remote.owner, owner, "{url}");
+            assert_eq!(remote.repo, repo, "{url}");
+            assert_eq!(remote.clone_url, clone_url, "{url}");
+            assert_eq!(remote.target, target, "{url}");
+        }
+    }
+
+    #[test]
+    fn parse_remote_url_api_urls() {
+        let cases = [
+            ("https://github.com/o/r", Some("https://api.github.com")),
+            ("https://gitlab.com/g/p", Some("https://gitlab.com/api/v4")),
+            (
+                "https://git.uni.edu/gitlab/g/p",
+                Some("https://git.uni.edu/gitlab/api/v4"),
+            ),
+            (
+                "https://stash.corp.com/scm/abc/thing.git",
+                Some("https://stash.corp.com/rest/api/1.0"),
+            ),
+            ("https://gitea.com/o/r", Some("https://gitea.com/api/v1")),
+            (
+                "https://git.uni.edu:8443/o/r",
+                Some("https://git.uni.edu:8443/api/v1"),
+            ),
+            (
+                "http://gitlab.lab.local:8080/g/p",
+                Some("http://gitlab.lab.local:8080/api/v4"),
+            ),
+            ("https://dev.azure.com/o/p/_git/r", None),
+        ];
+        let hosts = hosts();
+        for (url, expected) in cases {
+            let remote = parse_remote_url(url, &hosts).unwrap();
+            assert_eq!(remote.api_url.as_deref(), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn host_api_for_token_names() {
+        use HostKind::*;
+        let cases = [
+            ("github.com", Some((GitHub, "https://api.github.com"))),
+            ("GitLab.com", Some((GitLab, "https://gitlab.com/api/v4"))),
+            (
+                "git.uni.edu",
+                Some((GitLab, "https://git.uni.edu/gitlab/api/v4")),
+            ),
+            (
+                "gitlab.lab.local",
+                Some((GitLab, "https://gitlab.lab.local/api/v4")),
+            ),
+            (
+                "stash.corp.com",
+                Some((Bitbucket, "https://stash.corp.com/rest/api/1.0")),
+            ),
+            ("org.visualstudio.com", None),
+            ("gitlab.example.com", None),
+        ];
+        let hosts = hosts();
+        for (host, expected) in cases {
+            assert_eq!(
+                host_api(&hosts, host),
+                expected.map(|(kind, url)| (kind, url.to_string())),
+                "{host}"
+            );
+        }
+
+        // A host registered only with a port is found by its bare hostname
+        let hosts = vec![GitHost {
+            hostname: "git.uni.edu:8443".to_string(),
+            kind: Gitea,
+            path_prefix: None,
+            api_base_url: None,
+        }];
+        assert_eq!(
+            host_api(&hosts, "git.uni.edu"),
+            Some((Gitea, "https://git.uni.edu:8443/api/v1".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_remote_url_rejects_invalid_urls() {
+        let cases = [
+            "not a url",
+            "https://example.com/a/b",
+            "https://github.com/onlyowner",
+            "https://github.com/-bad/repo",
+            "https://gitlab.com/g",
+            "https://dev.azure.com/o/p/r",
+            "https://git.uni.edu/other/team/proj",
+        ];
+        let hosts = hosts();
+        for url in cases {
+            assert!(parse_remote_url(url, &hosts).is_err(), "{url} should fail");
+        }
+    }
+}
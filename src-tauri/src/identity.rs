@@ -0,0 +1,327 @@
+use git2::Repository;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::contributor::{Contacts, Contributor};
+use crate::forge_activity::{ForgeActivity, ForgeClient};
+use crate::git_hosts::HostKind;
+use crate::utils::{add_unique, to_string};
+
+// Each lookup is one API request, so large histories are only partly resolved
+const MAX_LOOKUPS: usize = 100;
+
+/// The login in a host's noreply address, e.g. `123+octocat@users.noreply.github.com` or
+/// `123-someone@users.noreply.gitlab.com`.
+pub fn decode_noreply(email: &str) -> Option<String> {
+    let (local, domain) = email.rsplit_once('@')?;
+    if !domain.to_lowercase().starts_with("users.noreply.") {
+        return None;
+    }
+    // Newer addresses start with the numeric user id
+    let login = match local.find(['+', '-']) {
+        Some(index) if local[..index].chars().all(|c| c.is_ascii_digit()) && index > 0 => {
+            &local[index + 1..]
+        }
+        _ => local,
+    };
+    (!login.is_empty()).then(|| login.to_string())
+}
+
+// The newest commit by each author email, which the host can link to a login
+fn commit_per_email(repo: &Repository) -> Result<HashMap<String, String>, String> {
+    let mut revwalk = repo.revwalk().map_err(to_string)?;
+    revwalk.push_head().map_err(to_string)?;
+
+    let mut commits = HashMap::new();
+    for oid in revwalk {
+        let oid = oid.map_err(to_string)?;
+        let commit = repo.find_commit(oid).map_err(to_string)?;
+        let email = commit.author().email().unwrap_or_default().to_lowercase();
+        commits.entry(email).or_insert_with(|| oid.to_string());
+    }
+    Ok(commits)
+}
+
+async fn lookup_login(
+    client: &ForgeClient,
+    email: &str,
+    commits: &HashMap<String, String>,
+) -> Result<Option<String>, String> {
+    let remote = &client.remote;
+    match client.kind() {
+        HostKind::GitHub => {
+            let Some(sha) = commits.get(&email.to_lowercase()) else {
+                return Ok(None);
+            };
+            let commit = client
+                .get(
+                    &format!("/repos/{}/{}/commits/{sha}", remote.owner, remote.repo),
+                    &[],
+                )
+                .await?;
+            Ok(commit
+                .get("author")
+                .and_then(|author| author.get("login"))
+                .and_then(Value::as_str)
+                .map(String::from))
+        }
+        HostKind::GitLab => {
+            // Only public emails can be searched, and only an unambiguous match is trusted
+            let users = client
+                .get("/users", &[("search", email.to_string())])
+                .await?;
+            Ok(match users.as_array().map(Vec::as_slice) {
+                Some([user]) => user
+                    .get("username")
+                    .and_then(Value::as_str)
+                    .map(String::from),
+                _ => None,
+            })
+        }
+        _ => Ok(None),
+    }
+}
+
+// Adds `other`'s stats and identities to `into`, keeping the name with the most commits
+fn merge_into(into: &mut Contributor, other: Contributor) {
+    let mut contacts = into.contacts.emails().to_vec();
+    add_unique(&mut contacts, other.contacts.emails());
+    into.contacts = Contacts::EmailList(contacts);
+    add_unique(&mut into.logins, &other.logins);
+    add_unique(&mut into.names, &other.names);
+    if let Some(ref activity) = other.forge_activity {
+        into.forge_activity
+            .get_or_insert_with(ForgeActivity::default)
+            .add(activity);
+    }
+
+    if other.total_commits > into.total_commits {
+        into.username = other.username;
+        into.profile_colour = other.profile_colour;
+        into.username_initials = other.username_initials;
+        into.ai_summary = other.ai_summary;
+    }
+    into.total_commits += other.total_commits;
+    into.additions += other.additions;
+    into.deletions += other.deletions;
+    into.total_regex_matches += other.total_regex_matches;
+    into.commits_matching_regex += other.commits_matching_regex;
+}
+
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// Contributors that share a host login merged into one, including through others: A with
+/// login x and B with login y are merged when C has both.
+pub fn merge_by_login(contributors: Vec<Contributor>) -> Vec<Contributor> {
+    // Union-find, where each group's root is its first contributor
+    let mut parents: Vec<usize> = (0..contributors.len()).collect();
+    let mut first_with_login: HashMap<&str, usize> = HashMap::new();
+    for (index, contributor) in contributors.iter().enumerate() {
+        for login in &contributor.logins {
+            let Some(&other) = first_with_login.get(login.as_str()) else {
+                first_with_login.insert(login, index);
+                continue;
+            };
+            let (a, b) = (
+                find_root(&mut parents, other),
+                find_root(&mut parents, index),
+            );
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+    let roots: Vec<usize> = (0..contributors.len())
+        .map(|index| find_root(&mut parents, index))
+        .collect();
+
+    let mut merged: Vec<Contributor> = Vec::new();
+    let mut merged_index: HashMap<usize, usize> = HashMap::new();
+    for (contributor, root) in contributors.into_iter().zip(roots) {
+        match merged_index.get(&root) {
+            Some(&index) => {
+                log::info!(
+                    "Merging {} into {}",
+                    contributor.username,
+                    merged[index].username
+                );
+                merge_into(&mut merged[index], contributor);
+            }
+            None => {
+                merged_index.insert(root, merged.len());
+                merged.push(contributor);
+            }
+        }
+    }
+    merged
+}
+
+/// Attaches host logins to contributors and merges those that are the same person. Logins are
+/// decoded from noreply emails, then, when the repository at `url` is on GitHub or GitLab and a
+/// token is available, looked up through the host's API for the remaining emails.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn resolve_contributor_identities(
+    path: &str,
+    contributors: Vec<Contributor>,
+    url: Option<&str>,
+    token: Option<&str>,
+) -> Result<Vec<Contributor>, String> {
+    let mut contributors = contributors;
+    for contributor in &mut contributors {
+        let logins: Vec<String> = contributor
+            .contacts
+            .emails()
+            .iter()
+            .filter_map(|email| decode_noreply(email))
+            .collect();
+        add_unique(&mut contributor.logins, &logins);
+        let username = [contributor.username.clone()];
+        add_unique(&mut contributor.names, &username);
+    }
+
+    // Without a token the rate limit is too low to look up every author
+    let client = match url.map(|url| ForgeClient::new(url, token)) {
+        Some(Ok(client)) if client.has_token() => Some(client),
+        Some(Err(e)) => {
+            log::warn!("Can't look up logins: {e}");
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(client) = client {
+        let canonical_path = std::path::Path::new(path)
+            .canonicalize()
+            .map_err(to_string)?;
+        let repo = Repository::open(canonical_path).map_err(to_string)?;
+        let commits = commit_per_email(&repo)?;
+
+        let mut lookups = 0;
+        'contributors: for contributor in contributors.iter_mut().filter(|c| c.logins.is_empty()) {
+            for email in contributor.contacts.emails().to_vec() {
+                if lookups == MAX_LOOKUPS {
+                    log::warn!("Stopped looking up logins after {MAX_LOOKUPS} requests");
+                    break 'contributors;
+                }
+                lookups += 1;
+                match lookup_login(&client, &email, &commits).await {
+                    Ok(Some(login)) => {
+                        add_unique(&mut contributor.logins, &[login]);
+                        break;
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Couldn't look up the login for {email}: {e}"),
+                }
+            }
+        }
+    }
+
+    Ok(merge_by_login(contributors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contributor(username: &str, email: &str, logins: &[&str], commits: u64) -> Contributor {
+        Contributor {
+            username: username.to_string(),
+            contacts: Contacts::Email(email.to_string()),
+            total_commits: commits,
+            additions: 0,
+            deletions: 0,
+            profile_colour: String::new(),
+            username_initials: String::new(),
+            total_regex_matches: 0,
+            commits_matching_regex: 0,
+            ai_summary: String::new(),
+            forge_activity: None,
+            logins: logins.iter().map(|l| l.to_string()).collect(),
+            names: vec![username.to_string()],
+        }
+    }
+
+    #[test]
+    fn decode_noreply_addresses() {
+        let cases = [
+            ("123+octocat@users.noreply.github.com", Some("octocat")),
+            ("octocat@users.noreply.github.com", Some("octocat")),
+            ("123-someone@users.noreply.gitlab.com", Some("someone")),
+            ("456+dash-name@Users.NoReply.GitHub.com", Some("dash-name")),
+            // Only a numeric prefix is an id
+            ("some-one@users.noreply.gitlab.com", Some("some-one")),
+            ("+name@users.noreply.github.com", Some("+name")),
+            ("123+@users.noreply.github.com", None),
+            ("octocat@github.com", None),
+            ("not an email", None),
+        ];
+        for (email, expected) in cases {
+            assert_eq!(
+                decode_noreply(email).as_deref(),
+                expected,
+                "email {email:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn merge_by_login_is_transitive() {
+        let cases = [
+            // C links A and B through both their logins
+            (
+                vec![
+                    contributor("A", "a@x", &["x"], 1),
+                    contributor("B", "b@x", &["y"], 2),
+                    contributor("C", "c@x", &["x", "y"], 5),
+                ],
+                vec![("C", 8)],
+            ),
+            // A chain, where only the last contributor links back to the first
+            (
+                vec![
+                    contributor("A", "a@x", &["w"], 3),
+                    contributor("B", "b@x", &["x"], 1),
+                    contributor("C", "c@x", &["x", "y"], 1),
+                    contributor("D", "d@x", &["y", "w"], 1),
+                ],
+                vec![("A", 6)],
+            ),
+            (
+                vec![
+                    contributor("A", "a@x", &["x"], 1),
+                    contributor("B", "b@x", &[], 1),
+                    contributor("C", "c@x", &[], 1),
+                ],
+                vec![("A", 1), ("B", 1), ("C", 1)],
+            ),
+            (
+                vec![
+                    contributor("A", "a@x", &["x"], 1),
+                    contributor("B", "b@x", &["y"], 1),
+                    contributor("C", "c@x", &["x"], 1),
+                ],
+                vec![("A", 2), ("B", 1)],
+            ),
+        ];
+        for (contributors, expected) in cases {
+            let merged = merge_by_login(contributors);
+            let actual: Vec<(&str, u64)> = merged
+                .iter()
+                .map(|c| (c.username.as_str(), c.total_commits))
+                .collect();
+            assert_eq!(actual, expected);
+        }
+
+        let merged = merge_by_login(vec![
+            contributor("A", "a@x", &["x"], 1),
+            contributor("B", "b@x", &["y"], 2),
+            contributor("C", "c@x", &["x", "y"], 5),
+        ]);
+        assert_eq!(merged[0].contacts.emails(), ["a@x", "b@x", "c@x"]);
+        assert_eq!(merged[0].logins, ["x", "y"]);
+    }
+}
@@ -1,16 +1,35 @@
 mod branches;
+mod bundle;
 mod contributor;
+mod credentials;
+mod forge_activity;
+mod git_hosts;
+mod identity;
+mod llm;
 mod manifest;
+mod offline_summary;
+mod prompt_templates;
+mod pseudonymise;
 mod repositories;
+mod settings;
+mod structured_summary;
 mod summary;
+mod summary_cache;
+mod summary_input;
+mod tags;
+mod team_summary;
 mod tools;
 mod url_verifier;
 mod utils;
+mod workspace;
 
 // use tauri_plugin_fs;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    let (data_directory, data_directory_source) = settings::init_data_directory(&args);
+
     // Check manifest on startup
     tauri::async_runtime::spawn(async {
         if let Err(e) = manifest::check_manifest().await {
@@ -26,12 +45,24 @@ pub fn run() {
         .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        // Runs after the plugins, so the log plugin can record this
+        .setup(move |_| {
+            log::info!("Using data directory {data_directory:?} ({data_directory_source:?})");
+            Ok(())
+        })
         .manage(summary::CancellationState::default())
         .invoke_handler(tauri::generate_handler![
             tools::get_app_version,
             branches::get_branch_names,
+            branches::count_branch_contributors,
+            tags::list_tags,
             contributor::check_regex,
             contributor::get_contributor_info,
+            contributor::get_contributor_info_by_release,
+            contributor::compare_branches,
+            forge_activity::get_forge_activity,
+            forge_activity::merge_forge_activity,
+            identity::resolve_contributor_identities,
             contributor::group_contributors_by_config,
             repositories::bare_clone,
             repositories::try_clone_with_token,
@@ -39,15 +70,52 @@ pub fn run() {
             repositories::delete_repo,
             repositories::get_local_repo_information,
             repositories::refresh_repo,
+            repositories::preflight_remote_repository,
+            repositories::import_archive,
             url_verifier::verify_and_extract_source_info,
+            url_verifier::detect_source_kind,
+            url_verifier::get_clone_path,
+            git_hosts::get_git_hosts,
+            git_hosts::set_git_hosts,
             manifest::read_manifest,
             manifest::save_manifest,
             summary::get_ai_summary,
             summary::get_ai_summary_with_config,
+            summary::preview_ai_summary_with_config,
             summary::cancel_summary_generation,
+            team_summary::get_team_summary,
+            summary_cache::clear_summary_cache,
             summary::gemini_key_validation,
             summary::check_key_set,
+            credentials::get_credential_store_status,
+            credentials::unlock_credential_store,
+            credentials::lock_credential_store,
+            credentials::set_credential,
+            credentials::list_credentials,
+            credentials::validate_credential,
+            credentials::delete_credential,
+            llm::get_llm_config,
+            llm::set_llm_config,
+            llm::get_llm_generation_options,
+            llm::set_llm_generation_options,
+            summary_input::get_summary_input_options,
+            summary_input::set_summary_input_options,
+            prompt_templates::list_prompt_templates,
+            prompt_templates::save_prompt_template,
+            prompt_templates::delete_prompt_template,
+            prompt_templates::set_active_prompt_template,
+            summary::preview_summary_prompt,
             manifest::get_working_directory,
+            settings::get_data_directory_info,
+            settings::move_data_directory,
+            workspace::list_workspaces,
+            workspace::create_workspace,
+            workspace::update_workspace,
+            workspace::switch_workspace,
+            workspace::archive_workspace,
+            workspace::export_workspace,
+            bundle::export_marking_bundle,
+            bundle::import_marking_bundle,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
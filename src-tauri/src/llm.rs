@@ -0,0 +1,697 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::credentials::{get_secret, CredentialKind, GEMINI_CREDENTIAL, OPENAI_CREDENTIAL};
+use crate::settings::{read_settings, write_settings};
+use crate::utils::to_string;
+
+/*
+The "llm" section of settings.json is one of
+{ "provider": "gemini", "models": ["gemini-2.0-flash", ...] }
+{ "provider": "open_ai_compatible", "base_url": "https://api.openai.com/v1", "models": [...] }
+{ "provider": "local", "server": "ollama" | "llama_cpp", "base_url": "http://localhost:11434", "models": [...] }
+{ "provider": "offline" } (See offline_summary.rs)
+*/
+
+pub const GEMINI_API_KEY_VAR: &str = "GEMINI_API_KEY";
+pub const OPENAI_API_KEY_VAR: &str = "OPENAI_API_KEY";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalServerKind {
+    Ollama,
+    LlamaCpp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum LlmConfig {
+    Gemini {
+        models: Vec<String>,
+    },
+    OpenAiCompatible {
+        base_url: String,
+        models: Vec<String>,
+    },
+    Local {
+        server: LocalServerKind,
+        base_url: String,
+        models: Vec<String>,
+    },
+    /// Heuristic summaries computed locally, for when no AI provider is available.
+    Offline,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig::Gemini {
+            models: vec![
+                "gemini-2.0-flash".to_string(),
+                "gemini-2.0-flash-lite".to_string(),
+                "gemini-2.5-flash-lite".to_string(),
+            ],
+        }
+    }
+}
+
+impl LlmConfig {
+    pub fn models(&self) -> &[String] {
+        match self {
+            LlmConfig::Gemini { models }
+            | LlmConfig::OpenAiCompatible { models, .. }
+            | LlmConfig::Local { models, .. } => models,
+            LlmConfig::Offline => &[],
+        }
+    }
+
+    /// The "provider" tag used in settings.json.
+    pub fn provider_name(&self) -> &'static str {
+        match self {
+            LlmConfig::Gemini { .. } => "gemini",
+            LlmConfig::OpenAiCompatible { .. } => "open_ai_compatible",
+            LlmConfig::Local { .. } => "local",
+            LlmConfig::Offline => "offline",
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if matches!(self, LlmConfig::Offline) {
+            return Ok(());
+        }
+        if self.models().iter().all(|m| m.trim().is_empty()) {
+            return Err("At least one model must be configured".to_string());
+        }
+        match self {
+            LlmConfig::Gemini { .. } | LlmConfig::Offline => Ok(()),
+            LlmConfig::OpenAiCompatible { base_url, .. } | LlmConfig::Local { base_url, .. } => {
+                url::Url::parse(base_url)
+                    .map(|_| ())
+                    .map_err(|e| format!("Invalid base URL '{base_url}': {e}"))
+            }
+        }
+    }
+}
+
+/// A chat/completion backend that can turn a prompt into text.
+pub trait LlmProvider {
+    /// Short identifier used in logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Models to try, in order, until one of them returns a response.
+    fn models(&self) -> &[String];
+
+    fn has_credentials(&self) -> bool;
+
+    fn generate(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        prompt: &str,
+    ) -> impl Future<Output = Result<String, ProviderError>> + Send;
+}
+
+pub struct GeminiProvider {
+    api_key: Option<String>,
+    models: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    content: Content,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    text: String,
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    async fn generate(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        prompt: &str,
+    ) -> Result<String, ProviderError> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or(ProviderError::fatal("Gemini API key is not set"))?;
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{model}:generateContent?key={api_key}"
+        );
+
+        let response = client
+            .post(&url)
+            .json(&json!({
+                "contents": [{
+                    "parts": [{"text": prompt}]
+                }]
+            }))
+            .send()
+            .await
+            .map_err(|e| describe_request_error(model, e))?;
+        check_status(model, &response)?;
+
+        let response_json = response
+            .json::<GeminiResponse>()
+            .await
+            .map_err(|e| parse_error(model, e))?;
+
+        response_json
+            .candidates
+            .first()
+            .and_then(|candidate| candidate.content.parts.first())
+            .map(|part| part.text.clone())
+            .ok_or(empty_response(model))
+    }
+}
+
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    api_key: Option<String>,
+    models: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        "open_ai_compatible"
+    }
+
+    fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    // Self-hosted OpenAI-compatible gateways often don't need a key
+    fn has_credentials(&self) -> bool {
+        true
+    }
+
+    async fn generate(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        prompt: &str,
+    ) -> Result<String, ProviderError> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+
+        let mut request = client.post(&url).json(&json!({
+            "model": model,
+            "messages": [{"role": "user", "content": prompt}]
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| describe_request_error(model, e))?;
+        check_status(model, &response)?;
+
+        let response_json = response
+            .json::<ChatCompletionResponse>()
+            .await
+            .map_err(|e| parse_error(model, e))?;
+
+        response_json
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or(empty_response(model))
+    }
+}
+
+pub struct LocalProvider {
+    server: LocalServerKind,
+    base_url: String,
+    models: Vec<String>,
+}
+
+impl LlmProvider for LocalProvider {
+    fn name(&self) -> &str {
+        match self.server {
+            LocalServerKind::Ollama => "ollama",
+            LocalServerKind::LlamaCpp => "llama_cpp",
+        }
+    }
+
+    fn models(&self) -> &[String] {
+        &self.models
+    }
+
+    fn has_credentials(&self) -> bool {
+        true
+    }
+
+    async fn generate(
+        &self,
+        client: &reqwest::Client,
+        model: &str,
+        prompt: &str,
+    ) -> Result<String, ProviderError> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let (url, body, field) = match self.server {
+            LocalServerKind::Ollama => (
+                format!("{base_url}/api/generate"),
+                json!({"model": model, "prompt": prompt, "stream": false}),
+                "response",
+            ),
+            // llama.cpp serves a single model, so the model name is only informational.
+            // n_predict is left to the server, as structured and team summaries are long.
+            LocalServerKind::LlamaCpp => (
+                format!("{base_url}/completion"),
+                json!({"prompt": prompt}),
+                "content",
+            ),
+        };
+
+        let response = client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| describe_request_error(model, e))?;
+        check_status(model, &response)?;
+
+        let response_json = response
+            .json::<Value>()
+            .await
+            .map_err(|e| parse_error(model, e))?;
+
+        response_json
+            .get(field)
+            .and_then(|text| text.as_str())
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .ok_or(empty_response(model))
+    }
+}
+
+#[derive(Debug)]
+pub struct ProviderError {
+    pub message: String,
+    /// Whether the same request may succeed later (rate limits, timeouts, server errors).
+    pub retryable: bool,
+    /// How long the provider asked us to wait, from the `Retry-After` header.
+    pub retry_after: Option<Duration>,
+}
+
+impl ProviderError {
+    fn fatal(message: &str) -> Self {
+        ProviderError {
+            message: message.to_string(),
+            retryable: false,
+            retry_after: None,
+        }
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+fn describe_request_error(model: &str, e: reqwest::Error) -> ProviderError {
+    let message = if e.is_timeout() {
+        format!("Request to model {model} timed out")
+    } else if e.is_connect() {
+        "Network connection error. Please check internet connection.".to_string()
+    } else if e.is_request() {
+        format!("Request error to model {model}. Please try again.")
+    } else {
+        format!("Request to model {model} failed. Unknown Error: {e}")
+    };
+    ProviderError {
+        message,
+        retryable: e.is_timeout() || e.is_connect(),
+        retry_after: None,
+    }
+}
+
+fn parse_error(model: &str, e: reqwest::Error) -> ProviderError {
+    ProviderError::fatal(&format!("Failed to parse response from model {model}: {e}"))
+}
+
+fn empty_response(model: &str) -> ProviderError {
+    ProviderError::fatal(&format!("Empty response from model {model}"))
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+fn check_status(model: &str, response: &reqwest::Response) -> Result<(), ProviderError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+
+    Err(ProviderError {
+        message: format!("Model {model} returned status {status}"),
+        retryable: status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+        retry_after,
+    })
+}
+
+const MAX_RETRIES: u32 = 10;
+const MIN_BACKOFF_MS: u64 = 100;
+const MAX_BACKOFF_MS: u64 = 120_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GenerationOptions {
+    /// Number of contributors summarised at the same time.
+    pub max_concurrent: usize,
+    pub requests_per_minute: u32,
+    /// Retries per model for rate-limited or failed requests before trying the next model.
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for GenerationOptions {
+    fn default() -> Self {
+        GenerationOptions {
+            max_concurrent: 3,
+            requests_per_minute: 15,
+            max_retries: 3,
+            initial_backoff_ms: 1000,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket shared by every request of a generation job.
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: u32, burst: usize) -> Self {
+        let capacity = burst.max(1) as f64;
+        RateLimiter {
+            capacity,
+            refill_per_second: f64::from(requests_per_minute.max(1)) / 60.0,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.last_refill = now;
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                self.refill(&mut bucket);
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back every request of the job for `duration`, e.g. after a 429 response.
+    pub async fn pause(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().await;
+        self.refill(&mut bucket);
+        bucket.tokens = bucket.tokens.min(0.0) - duration.as_secs_f64() * self.refill_per_second;
+    }
+}
+
+pub fn get_generation_options() -> GenerationOptions {
+    read_settings().generation
+}
+
+pub fn build_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30)) //Set a timeout of 30 seconds
+        .connect_timeout(Duration::from_secs(10)) // Set a connection timeout of 10 seconds
+        .build()
+        .map_err(|e| format!("Failed to build client: {e}"))
+}
+
+/// Tries each of the provider's models in order, returning the first successful response.
+/// Retryable failures are retried with exponential backoff (or after `Retry-After`) before
+/// falling back to the next model.
+pub async fn generate_with_fallback<P: LlmProvider + Sync>(
+    provider: &P,
+    prompt: &str,
+    limiter: &RateLimiter,
+    options: &GenerationOptions,
+) -> Result<String, String> {
+    let client = build_client()?;
+    let mut last_error: Option<String> = None;
+
+    for model in provider.models() {
+        let mut attempt = 0;
+        loop {
+            limiter.acquire().await;
+            match provider.generate(&client, model, prompt).await {
+                Ok(text) => return Ok(text),
+                Err(e) if e.retryable && attempt < options.max_retries => {
+                    // Settings can be edited by hand and a server's Retry-After can be a day
+                    // away, so both are capped here
+                    let backoff = e
+                        .retry_after
+                        .unwrap_or_else(|| {
+                            let factor = 2u64.checked_pow(attempt).unwrap_or(u64::MAX);
+                            Duration::from_millis(options.initial_backoff_ms.saturating_mul(factor))
+                        })
+                        .min(Duration::from_millis(MAX_BACKOFF_MS));
+                    log::warn!(
+                        "[{}] {e}. Retrying in {}ms (attempt {} of {})",
+                        provider.name(),
+                        backoff.as_millis(),
+                        attempt + 1,
+                        options.max_retries
+                    );
+                    if e.retry_after.is_some() {
+                        limiter.pause(backoff).await;
+                    } else {
+                        tokio::time::sleep(backoff).await;
+                    }
+                    attempt += 1;
+                }
+                Err(e) => {
+                    log::error!("[{}] {e}", provider.name());
+                    last_error = Some(e.message);
+                    break;
+                }
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        String::from("Failed to generate summary. Check internet connection or API key validity.")
+    }))
+}
+
+pub fn get_config() -> LlmConfig {
+    read_settings().llm
+}
+
+// The credential store first, then the environment so keys can still be supplied in CI
+fn read_key(credential: &str, var: &str) -> Option<String> {
+    get_secret(CredentialKind::LlmApiKey, credential)
+        .or_else(|| env::var(var).ok())
+        .filter(|key| !key.is_empty())
+}
+
+pub fn gemini_provider(models: Vec<String>) -> GeminiProvider {
+    GeminiProvider {
+        api_key: read_key(GEMINI_CREDENTIAL, GEMINI_API_KEY_VAR),
+        models,
+    }
+}
+
+/// Whether the configured provider has what it needs to send a request.
+pub fn provider_has_credentials(config: &LlmConfig) -> bool {
+    match config.clone() {
+        LlmConfig::Gemini { models } => gemini_provider(models).has_credentials(),
+        LlmConfig::OpenAiCompatible { base_url, models } => OpenAiCompatibleProvider {
+            base_url,
+            api_key: read_key(OPENAI_CREDENTIAL, OPENAI_API_KEY_VAR),
+            models,
+        }
+        .has_credentials(),
+        LlmConfig::Local {
+            server,
+            base_url,
+            models,
+        } => LocalProvider {
+            server,
+            base_url,
+            models,
+        }
+        .has_credentials(),
+        LlmConfig::Offline => true,
+    }
+}
+
+// USD per million input and output tokens at list prices. Only a rough guide, as prices change
+const MODEL_PRICES: [(&str, f64, f64); 9] = [
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gpt-4o", 2.50, 10.0),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.40, 1.60),
+];
+
+/// Rough cost in USD of sending the tokens to the first configured model. Local and offline
+/// providers are free; `None` when the model's pricing is unknown.
+pub fn estimate_cost(config: &LlmConfig, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    if matches!(config, LlmConfig::Local { .. } | LlmConfig::Offline) {
+        return Some(0.0);
+    }
+    let model = config.models().first()?;
+    let (_, input_price, output_price) = MODEL_PRICES.iter().find(|(name, _, _)| name == model)?;
+    Some((input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0)
+}
+
+/// Sends `prompt` to the configured provider.
+pub async fn generate(
+    config: &LlmConfig,
+    prompt: &str,
+    limiter: &RateLimiter,
+) -> Result<String, String> {
+    let options = get_generation_options();
+    match config.clone() {
+        LlmConfig::Gemini { models } => {
+            generate_with_fallback(&gemini_provider(models), prompt, limiter, &options).await
+        }
+        LlmConfig::OpenAiCompatible { base_url, models } => {
+            let provider = OpenAiCompatibleProvider {
+                base_url,
+                api_key: read_key(OPENAI_CREDENTIAL, OPENAI_API_KEY_VAR),
+                models,
+            };
+            generate_with_fallback(&provider, prompt, limiter, &options).await
+        }
+        LlmConfig::Local {
+            server,
+            base_url,
+            models,
+        } => {
+            let provider = LocalProvider {
+                server,
+                base_url,
+                models,
+            };
+            generate_with_fallback(&provider, prompt, limiter, &options).await
+        }
+        LlmConfig::Offline => {
+            Err("The offline summariser only supports contributor summaries".to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_llm_config() -> LlmConfig {
+    get_config()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_llm_config(config: LlmConfig) -> Result<(), String> {
+    config.validate()?;
+    let mut settings = read_settings();
+    settings.llm = config;
+    write_settings(&settings).map_err(to_string)
+}
+
+#[tauri::command]
+pub fn get_llm_generation_options() -> GenerationOptions {
+    get_generation_options()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_llm_generation_options(options: GenerationOptions) -> Result<(), String> {
+    if options.max_concurrent == 0 || options.requests_per_minute == 0 {
+        return Err("Concurrency and requests per minute must be at least 1".to_string());
+    }
+    let options = GenerationOptions {
+        max_retries: options.max_retries.min(MAX_RETRIES),
+        initial_backoff_ms: options
+            .initial_backoff_ms
+            .clamp(MIN_BACKOFF_MS, MAX_BACKOFF_MS),
+        ..options
+    };
+    let mut settings = read_settings();
+    settings.generation = options;
+    write_settings(&settings)
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use crate::utils::to_string;
+use crate::workspace;
 
 /*
 manifest.json is this format
@@ -22,22 +23,40 @@ manifest.json is this format
             } | Null,
             "grading_sheet": String | Null
         }
+    ],
+    "active_workspace": "<workspace-id>",
+    "workspaces": [
+        {
+            "id": "<workspace-id>",
+            "name": "<workspace-name>",
+            "unit": String | Null,
+            "semester": String | Null,
+            "archived": true/false,
+            "created_at": "<timestamp>",
+            "repository": [ ... ] (Only used while the workspace is not active)
+            "retention_days": <days>
+        }
     ]
 }
     */
 
-use dirs::data_dir;
+use crate::settings::data_directory;
 
 #[tauri::command]
 pub async fn get_working_directory() -> String {
-    let mut path = data_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push("gitgauge");
+    let path = data_directory();
     path.to_str().unwrap_or_default().to_string()
 }
 
-async fn get_manifest_path() -> PathBuf {
-    let mut path = data_dir().unwrap_or_else(|| PathBuf::from(".")); // Fallback to current dir if data_dir fails
-    path.push("gitgauge");
+// Cached results (e.g. AI summaries) that can be regenerated if deleted
+pub async fn get_cache_directory() -> PathBuf {
+    let mut path = PathBuf::from(get_working_directory().await);
+    path.push("cache");
+    path
+}
+
+pub async fn get_manifest_path() -> PathBuf {
+    let mut path = data_directory();
     path.push("manifest.json");
     log::info!("Manifest path: {path:?}");
     path
@@ -72,33 +91,65 @@ async fn create_manifest() -> Result<(), String> {
 
 pub async fn check_manifest() -> Result<(), String> {
     if !get_manifest_path().await.exists() {
-        return create_manifest().await;
+        create_manifest().await?;
     }
-    let manifest = read_manifest().await?;
-    let mut manifest_changed = false;
-    let mut updated_repos = Vec::new();
+    let mut manifest = read_manifest().await?;
+    let mut manifest_changed = workspace::ensure_default_workspace(&mut manifest)?;
 
-    if let Some(repos) = manifest.get("repository").and_then(|r| r.as_array()) {
-        for repo in repos {
-            match check_repository(repo).await {
-                Ok(()) => {
-                    // Repository should stay in manifest
-                    updated_repos.push(repo.clone());
-                }
-                Err(should_delete_directory) => {
-                    // Repository should be removed from manifest
+    let active_id = workspace::get_active_workspace_id(&manifest);
+    let mut workspaces = workspace::get_workspaces(&manifest)?;
+
+    for ws in workspaces.iter_mut() {
+        // Archived workspaces keep their repositories until they are unarchived
+        if ws.archived {
+            continue;
+        }
+
+        if ws.id == active_id {
+            if let Some(repos) = manifest.get("repository").and_then(|r| r.as_array()) {
+                if let Some(kept) = prune_repositories(repos, ws.retention_days).await {
+                    manifest["repository"] = serde_json::Value::Array(kept);
                     manifest_changed = true;
+                }
+            }
+        } else if let Some(kept) = prune_repositories(&ws.repository, ws.retention_days).await {
+            ws.repository = kept;
+            manifest_changed = true;
+        }
+    }
 
-                    // Delete directory only if cloned=true and >30 days
-                    if should_delete_directory {
-                        if let Some(path_str) = repo.get("path").and_then(|p| p.as_str()) {
-                            let repo_path = PathBuf::from(path_str);
-                            if repo_path.is_dir() {
-                                if let Err(e) = std::fs::remove_dir_all(&repo_path) {
-                                    eprintln!(
-                                        "Failed to delete repository directory {path_str}: {e}"
-                                    );
-                                }
+    if manifest_changed {
+        workspace::set_workspaces(&mut manifest, &workspaces)?;
+        save_manifest_file(&manifest).await?;
+    }
+    Ok(())
+}
+
+// Returns the repositories that should stay in the manifest, or None if nothing was removed.
+async fn prune_repositories(
+    repos: &[serde_json::Value],
+    retention_days: i64,
+) -> Option<Vec<serde_json::Value>> {
+    let mut changed = false;
+    let mut updated_repos = Vec::new();
+
+    for repo in repos {
+        match check_repository(repo, retention_days).await {
+            Ok(()) => {
+                // Repository should stay in manifest
+                updated_repos.push(repo.clone());
+            }
+            Err(should_delete_directory) => {
+                // Repository should be removed from manifest
+                changed = true;
+
+                // Delete directory only if cloned=true and past the retention period
+                if should_delete_directory {
+                    if let Some(path_str) = repo.get("path").and_then(|p| p.as_str()) {
+                        let repo_path = PathBuf::from(path_str);
+                        if repo_path.is_dir() {
+                            if let Err(e) = std::fs::remove_dir_all(&repo_path) {
+                                eprintln!("Failed to delete repository directory {path_str}: {e}");
                             }
                         }
                     }
@@ -107,20 +158,10 @@ pub async fn check_manifest() -> Result<(), String> {
         }
     }
 
-    if manifest_changed {
-        let mut new_manifest = manifest.clone();
-        new_manifest["repository"] = serde_json::Value::Array(updated_repos);
-        let path = get_manifest_path().await;
-        std::fs::write(
-            path,
-            serde_json::to_string_pretty(&new_manifest).map_err(to_string)?,
-        )
-        .map_err(to_string)?;
-    }
-    Ok(())
+    changed.then_some(updated_repos)
 }
 
-async fn check_repository(repo: &serde_json::Value) -> Result<(), bool> {
+async fn check_repository(repo: &serde_json::Value, retention_days: i64) -> Result<(), bool> {
     // If repository is bookmarked, it always stays in the manifest
     if let Some(bookmarked) = repo.get("bookmarked").and_then(|b| b.as_bool()) {
         if bookmarked {
@@ -134,19 +175,19 @@ async fn check_repository(repo: &serde_json::Value) -> Result<(), bool> {
         }
     }
 
-    // Check if the repository has been accessed within 30 days
+    // Check if the repository has been accessed within the retention period
     if let Some(last_accessed) = repo.get("last_accessed").and_then(|l| l.as_str()) {
         match chrono::DateTime::parse_from_rfc3339(last_accessed) {
             Ok(last_accessed_time) => {
                 let now = chrono::Utc::now();
                 log::info!("Last accessed: {last_accessed_time}, Now: {now}");
 
-                // If accessed within 30 days, keep the repository
-                if now.signed_duration_since(last_accessed_time).num_days() < 30 {
+                // If accessed within the retention period, keep the repository
+                if now.signed_duration_since(last_accessed_time).num_days() < retention_days {
                     return Ok(());
                 }
 
-                // If older than 30 days and not bookmarked, determine cleanup action
+                // If older than the retention period and not bookmarked, determine cleanup action
                 let cloned = repo
                     .get("cloned")
                     .and_then(|c| c.as_bool())
@@ -176,7 +217,7 @@ async fn check_repository(repo: &serde_json::Value) -> Result<(), bool> {
     Err(cloned)
 }
 
-async fn save_manifest_file(manifest: &serde_json::Value) -> Result<(), String> {
+pub async fn save_manifest_file(manifest: &serde_json::Value) -> Result<(), String> {
     let path = get_manifest_path().await;
     std::fs::write(
         path,
@@ -190,6 +231,18 @@ async fn save_manifest_file(manifest: &serde_json::Value) -> Result<(), String>
 // object (e.g. invoke('save_manifest', { manifest: $manifest })). This wrapper
 // forwards to the internal file writer.
 #[tauri::command(rename_all = "snake_case")]
-pub async fn save_manifest(manifest: serde_json::Value) -> Result<(), String> {
+pub async fn save_manifest(mut manifest: serde_json::Value) -> Result<(), String> {
+    // The frontend store only tracks the active workspace's repositories, so keep the
+    // workspace bookkeeping from the file on disk when it isn't sent back.
+    if get_manifest_path().await.exists() {
+        let existing = read_manifest().await?;
+        for key in ["active_workspace", "workspaces"] {
+            if manifest.get(key).is_none() {
+                if let Some(value) = existing.get(key) {
+                    manifest[key] = value.clone();
+                }
+            }
+        }
+    }
     save_manifest_file(&manifest).await
 }
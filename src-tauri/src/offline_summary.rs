@@ -0,0 +1,336 @@
+use git2::{Oid, Repository};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+use crate::structured_summary::{NotableContribution, StructuredSummary, WorkSplit};
+use crate::summary_input::{
+    area_of, collect_changes, is_generated, CommitChanges, SummaryInputOptions,
+};
+use crate::utils::to_string;
+
+// "feat(parser)!: ..." -> "feat"
+static CONVENTIONAL_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z]+)(\([^)]*\))?!?:").unwrap());
+
+static WORD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z][A-Za-z0-9_-]+").unwrap());
+
+// Words that say nothing about what the work was about
+const STOP_WORDS: [&str; 48] = [
+    "the", "and", "for", "with", "from", "into", "that", "this", "some", "more", "when", "then",
+    "also", "added", "adding", "add", "update", "updated", "updates", "updating", "change",
+    "changed", "changes", "fix", "fixed", "fixes", "fixing", "remove", "removed", "minor", "small",
+    "new", "use", "now", "make", "made", "merge", "branch", "pull", "request", "main", "master",
+    "wip", "file", "files", "code", "work", "stuff",
+];
+
+const CONVENTIONAL_TYPES: [&str; 11] = [
+    "feat", "fix", "test", "docs", "refactor", "perf", "style", "chore", "build", "ci", "revert",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Category {
+    Feature,
+    Bugfix,
+    Test,
+    Docs,
+    Refactor,
+}
+
+const CATEGORIES: [Category; 5] = [
+    Category::Feature,
+    Category::Bugfix,
+    Category::Test,
+    Category::Docs,
+    Category::Refactor,
+];
+
+impl Category {
+    fn describe(self) -> &'static str {
+        match self {
+            Category::Feature => "building new features",
+            Category::Bugfix => "fixing bugs",
+            Category::Test => "writing tests",
+            Category::Docs => "writing documentation",
+            Category::Refactor => "refactoring and maintenance",
+        }
+    }
+}
+
+fn is_test_file(path: &str) -> bool {
+    let path = path.to_lowercase();
+    let file_name = path.rsplit('/').next().unwrap_or(&path);
+    path.split('/')
+        .any(|part| matches!(part, "test" | "tests" | "__tests__" | "spec" | "e2e"))
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+        || file_name.contains("_test.")
+        || file_name.starts_with("test_")
+}
+
+fn is_docs_file(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.starts_with("docs/")
+        || path.ends_with(".md")
+        || path.ends_with(".rst")
+        || path.ends_with(".txt")
+}
+
+fn language_of(extension: &str) -> String {
+    match extension {
+        "rs" => "Rust",
+        "ts" | "tsx" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "svelte" => "Svelte",
+        "vue" => "Vue",
+        "py" => "Python",
+        "java" => "Java",
+        "kt" => "Kotlin",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "hpp" => "C++",
+        "cs" => "C#",
+        "go" => "Go",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "html" => "HTML",
+        "css" | "scss" => "CSS",
+        "sql" => "SQL",
+        "md" => "Markdown",
+        "json" | "yaml" | "yml" | "toml" => "configuration",
+        other => return format!(".{other}"),
+    }
+    .to_string()
+}
+
+fn categorise(commit: &CommitChanges) -> Category {
+    if let Some(caps) = CONVENTIONAL_PREFIX.captures(&commit.message) {
+        match caps[1].to_lowercase().as_str() {
+            "feat" | "feature" => return Category::Feature,
+            "fix" | "bugfix" | "hotfix" | "revert" => return Category::Bugfix,
+            "test" | "tests" => return Category::Test,
+            "docs" | "doc" => return Category::Docs,
+            "refactor" | "perf" | "style" | "chore" | "build" | "ci" => return Category::Refactor,
+            _ => {}
+        }
+    }
+
+    // Without a prefix, go by the files touched and then the wording of the message
+    let files: Vec<&str> = commit
+        .files
+        .iter()
+        .filter(|f| !is_generated(&f.path))
+        .map(|f| f.path.as_str())
+        .collect();
+    if !files.is_empty() && files.iter().all(|f| is_test_file(f)) {
+        return Category::Test;
+    }
+    if !files.is_empty() && files.iter().all(|f| is_docs_file(f)) {
+        return Category::Docs;
+    }
+
+    let message = commit.message.to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|w| message.contains(w));
+    if mentions(&["fix", "bug", "resolve", "patch", "error", "issue", "crash"]) {
+        Category::Bugfix
+    } else if mentions(&[
+        "refactor", "clean", "rename", "move", "tidy", "format", "lint",
+    ]) {
+        Category::Refactor
+    } else if mentions(&["test"]) {
+        Category::Test
+    } else if mentions(&["readme", "docs", "document", "comment"]) {
+        Category::Docs
+    } else {
+        Category::Feature
+    }
+}
+
+// Most frequent meaningful words, counted once per commit
+fn keywords(commits: &[CommitChanges], count: usize) -> Vec<String> {
+    let mut frequency: HashMap<String, usize> = HashMap::new();
+    for commit in commits {
+        let message = CONVENTIONAL_PREFIX.replace(&commit.message, "");
+        let words: HashSet<String> = WORD
+            .find_iter(&message)
+            .map(|w| w.as_str().to_lowercase())
+            .filter(|w| w.len() >= 4)
+            .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+            .filter(|w| !CONVENTIONAL_TYPES.contains(&w.as_str()))
+            .collect();
+        for word in words {
+            *frequency.entry(word).or_default() += 1;
+        }
+    }
+
+    let mut words: Vec<(String, usize)> = frequency.into_iter().filter(|(_, n)| *n > 1).collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    // Skip plurals of words already picked, e.g. "summaries" after "summary"
+    let stem = |w: &str| match w.strip_suffix("ies") {
+        Some(base) => format!("{base}y"),
+        None => w.strip_suffix('s').unwrap_or(w).to_string(),
+    };
+    let mut picked: Vec<String> = Vec::new();
+    for (word, _) in words {
+        if picked.len() == count {
+            break;
+        }
+        if !picked.iter().any(|p| stem(p) == stem(&word)) {
+            picked.push(word);
+        }
+    }
+    picked
+}
+
+// Names with the largest totals, largest first
+fn top(counts: HashMap<String, usize>, count: usize) -> Vec<String> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().filter(|(_, n)| *n > 0).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .take(count)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+// Whole percentages that add up to exactly 100 (largest remainder rounding)
+fn percentages(weights: &[usize]) -> Vec<u32> {
+    let total: usize = weights.iter().sum();
+    if total == 0 {
+        return vec![0; weights.len()];
+    }
+    let mut result: Vec<u32> = weights.iter().map(|w| (w * 100 / total) as u32).collect();
+    let mut remainders: Vec<(usize, usize)> = weights
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (i, w * 100 % total))
+        .collect();
+    remainders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let missing = 100 - result.iter().sum::<u32>() as usize;
+    for (i, _) in remainders.into_iter().take(missing) {
+        result[i] += 1;
+    }
+    result
+}
+
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+fn summarize_changes(commits: &[CommitChanges]) -> StructuredSummary {
+    let mut area_lines: HashMap<String, usize> = HashMap::new();
+    let mut language_lines: HashMap<String, usize> = HashMap::new();
+    let mut test_lines = 0;
+    let mut total_lines = 0;
+    for file in commits.iter().flat_map(|c| c.files.iter()) {
+        if is_generated(&file.path) {
+            continue;
+        }
+        let lines = file.additions + file.deletions;
+        *area_lines.entry(area_of(&file.path)).or_default() += lines;
+        if let Some((_, ext)) = file.path.rsplit_once('.') {
+            *language_lines
+                .entry(language_of(&ext.to_lowercase()))
+                .or_default() += lines;
+        }
+        if is_test_file(&file.path) {
+            test_lines += lines;
+        }
+        total_lines += lines;
+    }
+
+    // Weighted by size so one large feature outweighs several typo fixes
+    let mut category_weights: HashMap<Category, usize> = HashMap::new();
+    for commit in commits {
+        *category_weights.entry(categorise(commit)).or_default() += commit.size() + 1;
+    }
+    let weights: Vec<usize> = CATEGORIES
+        .iter()
+        .map(|c| category_weights.get(c).copied().unwrap_or(0))
+        .collect();
+    let split = percentages(&weights);
+    let main_category = CATEGORIES
+        .iter()
+        .zip(&split)
+        .max_by_key(|(_, pct)| **pct)
+        .map(|(c, _)| *c)
+        .unwrap_or(Category::Feature);
+
+    let areas = top(area_lines, 5);
+    let languages = top(language_lines, 2);
+    let topics = keywords(commits, 3);
+    let test_percent = (test_lines * 100).checked_div(total_lines).unwrap_or(0);
+
+    let mut summary = if areas.is_empty() {
+        String::from("Worked across the repository")
+    } else {
+        format!(
+            "Worked mainly on {}",
+            join_list(&areas[..areas.len().min(2)])
+        )
+    };
+    if !languages.is_empty() {
+        summary.push_str(&format!(" ({})", join_list(&languages)));
+    }
+    summary.push_str(&format!(", mostly {}.", main_category.describe()));
+    if topics.is_empty() {
+        summary.push_str(&format!(" {test_percent}% of changed lines were tests."));
+    } else {
+        summary.push_str(&format!(
+            " Commits focused on {}; {test_percent}% of changed lines were tests.",
+            join_list(&topics)
+        ));
+    }
+
+    let mut largest: Vec<&CommitChanges> = commits.iter().filter(|c| c.size() > 0).collect();
+    largest.sort_by_key(|c| std::cmp::Reverse(c.size()));
+    let notable_contributions = largest
+        .iter()
+        .take(3)
+        .map(|c| NotableContribution {
+            description: c.message.clone(),
+            commits: vec![c.oid.to_string()],
+        })
+        .collect();
+    let evidence_commits = largest.iter().take(5).map(|c| c.oid.to_string()).collect();
+
+    StructuredSummary {
+        summary,
+        work_areas: areas,
+        work_split: WorkSplit {
+            feature: split[0],
+            bugfix: split[1],
+            test: split[2],
+            docs: split[3],
+            refactor: split[4],
+        },
+        notable_contributions,
+        evidence_commits,
+    }
+}
+
+/// Describes the commits without an AI provider, from the directories and file types they
+/// touch, their Conventional Commit prefixes, common words in their messages and how much of
+/// the change was tests. The same commits always give the same summary.
+pub fn summarize(repo_path: &str, oids: &[Oid]) -> Result<StructuredSummary, String> {
+    if oids.is_empty() {
+        return Err("No commits to summarise".to_string());
+    }
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+    // Only line counts are needed, so skip the diff excerpts
+    let options = SummaryInputOptions {
+        max_hunks_per_file: 0,
+        ..SummaryInputOptions::default()
+    };
+    let commits = oids
+        .iter()
+        .map(|oid| collect_changes(&repo, *oid, &options))
+        .collect::<Result<Vec<CommitChanges>, git2::Error>>()
+        .map_err(to_string)?;
+    Ok(summarize_changes(&commits))
+}
@@ -0,0 +1,321 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use crate::contributor::DateRange;
+use crate::settings::{data_directory, read_settings, write_settings};
+use crate::utils::{format_date, to_string};
+
+/*
+Prompt templates are markdown files in <data directory>/prompts, named <template name>.md.
+The built-in template is called "default" and can't be overwritten or deleted.
+Templates can use these variables:
+    {commits}      - overview, commit history and diff excerpts (required)
+    {file_summary} - just the overview of the areas and file types changed
+    {contributor}  - contributor name, or the mapped user name
+    {repo}         - repository name
+    {date_range}   - the dashboard date range, e.g. "2025-03-01 to 2025-05-30"
+    */
+
+pub const DEFAULT_TEMPLATE_NAME: &str = "default";
+pub const DEFAULT_TEMPLATE: &str = include_str!("AI-summary-prompt.md");
+
+const VARIABLES: [&str; 5] = [
+    "commits",
+    "file_summary",
+    "contributor",
+    "repo",
+    "date_range",
+];
+
+static VARIABLE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+#[derive(Serialize, Debug)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub content: String,
+    pub builtin: bool,
+    pub active: bool,
+    /// Why the template can't be used, if it fails validation.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PromptVariables {
+    pub commits: String,
+    pub file_summary: String,
+    pub contributor: String,
+    pub repo: String,
+    pub date_range: String,
+    /// Only used by the team summary prompt, so templates can't use it.
+    pub members: String,
+}
+
+impl PromptVariables {
+    /// Substitutes the variables in a single pass, so braces in commit messages or diffs are
+    /// never treated as variables.
+    pub fn render(&self, template: &str) -> String {
+        VARIABLE_PATTERN
+            .replace_all(template, |caps: &Captures| match &caps[1] {
+                "commits" => self.commits.clone(),
+                "file_summary" => self.file_summary.clone(),
+                "contributor" => self.contributor.clone(),
+                "repo" => self.repo.clone(),
+                "date_range" => self.date_range.clone(),
+                "members" => self.members.clone(),
+                _ => caps[0].to_string(),
+            })
+            .into_owned()
+    }
+}
+
+pub fn describe_date_range(date_range: Option<&DateRange>) -> String {
+    match date_range {
+        Some(range) => format!("{} to {}", format_date(range.start), format_date(range.end)),
+        None => String::from("all time"),
+    }
+}
+
+pub fn repo_name(repo_path: &str) -> String {
+    let path = repo_path.trim_end_matches(['/', '\\']);
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    name.trim_end_matches(".git").to_string()
+}
+
+fn get_prompts_directory() -> PathBuf {
+    data_directory().join("prompts")
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid template name \"{name}\": use only letters, numbers, '-' and '_'"
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the template only uses known variables and includes the commits.
+pub fn validate_template(content: &str) -> Result<(), String> {
+    let unknown: Vec<String> = VARIABLE_PATTERN
+        .captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| !VARIABLES.contains(&name.as_str()))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown variables {}. Available variables are {}",
+            unknown
+                .iter()
+                .map(|name| format!("{{{name}}}"))
+                .collect::<Vec<String>>()
+                .join(", "),
+            VARIABLES
+                .iter()
+                .map(|name| format!("{{{name}}}"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+    if !content.contains("{commits}") {
+        return Err("Template must include {commits}".to_string());
+    }
+    Ok(())
+}
+
+pub fn load_template(name: &str) -> Result<String, String> {
+    if name == DEFAULT_TEMPLATE_NAME {
+        return Ok(DEFAULT_TEMPLATE.to_string());
+    }
+    validate_name(name)?;
+    let path = get_prompts_directory().join(format!("{name}.md"));
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read template \"{name}\": {e}"))
+}
+
+/// The selected template, falling back to the built-in one if it is missing or invalid.
+pub fn active_template() -> String {
+    let name = active_template_name();
+    match load_template(&name).and_then(|content| validate_template(&content).map(|_| content)) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Using the default prompt template: {e}");
+            DEFAULT_TEMPLATE.to_string()
+        }
+    }
+}
+
+fn active_template_name() -> String {
+    read_settings()
+        .prompt_template
+        .unwrap_or_else(|| DEFAULT_TEMPLATE_NAME.to_string())
+}
+
+#[tauri::command]
+pub fn list_prompt_templates() -> Result<Vec<PromptTemplate>, String> {
+    let active = active_template_name();
+    let mut templates = vec![PromptTemplate {
+        name: DEFAULT_TEMPLATE_NAME.to_string(),
+        content: DEFAULT_TEMPLATE.to_string(),
+        builtin: true,
+        active: active == DEFAULT_TEMPLATE_NAME,
+        error: None,
+    }];
+
+    let dir = get_prompts_directory();
+    if !dir.exists() {
+        return Ok(templates);
+    }
+
+    let mut user_templates = Vec::new();
+    for entry in std::fs::read_dir(&dir).map_err(to_string)? {
+        let path = entry.map_err(to_string)?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if name == DEFAULT_TEMPLATE_NAME || validate_name(name).is_err() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path).map_err(to_string)?;
+        user_templates.push(PromptTemplate {
+            name: name.to_string(),
+            error: validate_template(&content).err(),
+            active: active == name,
+            content,
+            builtin: false,
+        });
+    }
+    user_templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates.extend(user_templates);
+    Ok(templates)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn save_prompt_template(name: &str, content: &str) -> Result<(), String> {
+    if name == DEFAULT_TEMPLATE_NAME {
+        return Err("The default template can't be overwritten".to_string());
+    }
+    validate_name(name)?;
+    validate_template(content)?;
+
+    let dir = get_prompts_directory();
+    std::fs::create_dir_all(&dir).map_err(to_string)?;
+    std::fs::write(dir.join(format!("{name}.md")), content).map_err(|e| {
+        log::error!("Failed to save prompt template {name}: {e}");
+        format!("Failed to save prompt template: {e}")
+    })?;
+    log::info!("Saved prompt template {name}");
+    Ok(())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn delete_prompt_template(name: &str) -> Result<(), String> {
+    if name == DEFAULT_TEMPLATE_NAME {
+        return Err("The default template can't be deleted".to_string());
+    }
+    validate_name(name)?;
+    std::fs::remove_file(get_prompts_directory().join(format!("{name}.md"))).map_err(to_string)?;
+
+    // Go back to the default rather than silently falling back on every summary
+    let mut settings = read_settings();
+    if settings.prompt_template.as_deref() == Some(name) {
+        settings.prompt_template = None;
+        write_settings(&settings)?;
+    }
+    log::info!("Deleted prompt template {name}");
+    Ok(())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_active_prompt_template(name: &str) -> Result<(), String> {
+    validate_template(&load_template(name)?)?;
+    let mut settings = read_settings();
+    settings.prompt_template = if name == DEFAULT_TEMPLATE_NAME {
+        None
+    } else {
+        Some(name.to_string())
+    };
+    write_settings(&settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> PromptVariables {
+        PromptVariables {
+            commits: "fix {repo} parsing".to_string(),
+            file_summary: "src/ (3 files)".to_string(),
+            contributor: "Ada {contributor}".to_string(),
+            repo: "gitgauge".to_string(),
+            date_range: "all time".to_string(),
+            members: "- {commits} <a@b.c>".to_string(),
+        }
+    }
+
+    #[test]
+    fn render_substitutes_variables() {
+        let cases = [
+            ("{contributor} in {repo}", "Ada {contributor} in gitgauge"),
+            (
+                "{file_summary}\n{commits}",
+                "src/ (3 files)\nfix {repo} parsing",
+            ),
+            ("{repo}{repo}", "gitgaugegitgauge"),
+            ("From {date_range}", "From all time"),
+            ("no variables", "no variables"),
+            ("{unknown} and {repo}", "{unknown} and gitgauge"),
+            ("{ repo } and {1repo}", "{ repo } and {1repo}"),
+            ("{{repo}}", "{gitgauge}"),
+            (
+                "{members}\n{commits}",
+                "- {commits} <a@b.c>\nfix {repo} parsing",
+            ),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                variables().render(template),
+                expected,
+                "template {template:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_template_checks_variables() {
+        let cases = [
+            ("{commits}", true),
+            ("{contributor}: {commits} ({date_range})", true),
+            ("{contributor} only", false),
+            ("{commits} {author}", false),
+        ];
+        for (template, valid) in cases {
+            assert_eq!(
+                validate_template(template).is_ok(),
+                valid,
+                "template {template:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn repo_name_strips_path_and_extension() {
+        let cases = [
+            ("/data/repositories/0-owner-repo", "0-owner-repo"),
+            ("/data/repositories/repo.git/", "repo"),
+            ("C:\\Users\\me\\repo", "repo"),
+            ("repo", "repo"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(repo_name(path), expected, "path {path:?}");
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use git2::{Oid, Repository};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use crate::structured_summary::StructuredSummary;
+use crate::utils::to_string;
+
+static EMAIL_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+
+// Aliases use a reserved domain so they are never mistaken for real addresses
+const ALIAS_DOMAIN: &str = "example.invalid";
+
+// Shorter names match too many ordinary words to replace safely
+const MIN_NAME_LENGTH: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// Authors and committers of the given commits.
+pub fn collect_identities(repo_path: &str, oids: &[Oid]) -> Result<Vec<Identity>, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+    let mut identities = BTreeSet::new();
+    for oid in oids {
+        let commit = repo.find_commit(*oid).map_err(to_string)?;
+        for signature in [commit.author(), commit.committer()] {
+            identities.insert(Identity {
+                name: signature.name().map(String::from),
+                email: signature.email().map(|e| e.to_lowercase()),
+            });
+        }
+    }
+    Ok(identities.into_iter().collect())
+}
+
+/// Emails in `text` other than aliases.
+pub fn find_emails(text: &str) -> Vec<String> {
+    let emails: BTreeSet<String> = EMAIL_PATTERN
+        .find_iter(text)
+        .map(|m| m.as_str().to_lowercase())
+        .filter(|e| !e.ends_with(ALIAS_DOMAIN))
+        .collect();
+    emails.into_iter().collect()
+}
+
+/// Names and emails of `identities` that appear in `text`, plus any other emails.
+pub fn find_identities(text: &str, identities: &[Identity]) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let mut found: BTreeSet<String> = find_emails(text).into_iter().collect();
+    for identity in identities {
+        if let Some(ref name) = identity.name {
+            if name.len() >= MIN_NAME_LENGTH && name_pattern(name).is_match(text) {
+                found.insert(name.clone());
+            }
+        }
+        if let Some(ref email) = identity.email {
+            if lower.contains(email.as_str()) {
+                found.insert(email.clone());
+            }
+        }
+    }
+    found.into_iter().collect()
+}
+
+fn name_pattern(name: &str) -> Regex {
+    Regex::new(&format!(r"\b{}\b", regex::escape(name))).unwrap()
+}
+
+const MIN_ALIAS_LENGTH: usize = 4;
+
+// Stable across runs and repositories, so the same person always gets the same alias. `taken`
+// maps the ids handed out so far to their keys; an id another key already has is lengthened
+// until it is unique, so two people never share an alias.
+fn alias_id(key: &str, taken: &HashMap<String, String>) -> String {
+    let key = key.to_lowercase();
+    let mut context = md5::Context::new();
+    context.consume(&key);
+    let hash = format!("{:x}", context.finalize());
+
+    for length in (MIN_ALIAS_LENGTH..=hash.len()).step_by(2) {
+        let id = &hash[..length];
+        match taken.get(id) {
+            Some(owner) if *owner != key => continue,
+            _ => return id.to_string(),
+        }
+    }
+    // Only reached by keys with the same md5
+    (2..)
+        .map(|counter| format!("{hash}-{counter}"))
+        .find(|id| taken.get(id).is_none_or(|owner| *owner == key))
+        .unwrap_or(hash)
+}
+
+/// Replaces real names and emails with stable aliases such as "Person 3fa2" and
+/// "person-3fa2@example.invalid", and puts them back in the model's response.
+pub struct Pseudonymiser {
+    pattern: Option<Regex>,
+    aliases: HashMap<String, String>,
+    restore_pattern: Option<Regex>,
+    originals: HashMap<String, String>,
+}
+
+impl Pseudonymiser {
+    pub fn new(identities: &[Identity]) -> Self {
+        // Real value (lowercased for emails) -> alias
+        let mut names: BTreeMap<String, String> = BTreeMap::new();
+        let mut emails: BTreeMap<String, String> = BTreeMap::new();
+        let mut ids: HashMap<String, String> = HashMap::new();
+        for identity in identities {
+            let Some(key) = identity.email.as_ref().or(identity.name.as_ref()) else {
+                continue;
+            };
+            let id = alias_id(key, &ids);
+            ids.insert(id.clone(), key.to_lowercase());
+            if let Some(ref email) = identity.email {
+                emails
+                    .entry(email.to_lowercase())
+                    .or_insert_with(|| format!("person-{id}@{ALIAS_DOMAIN}"));
+            }
+            if let Some(ref name) = identity.name {
+                if name.len() >= MIN_NAME_LENGTH {
+                    names
+                        .entry(name.clone())
+                        .or_insert_with(|| format!("Person {id}"));
+                }
+            }
+        }
+
+        let mut aliases = HashMap::new();
+        let mut originals = HashMap::new();
+        let mut alternatives: Vec<(usize, String)> = Vec::new();
+        for (email, alias) in emails {
+            alternatives.push((email.len(), format!("(?i:{})", regex::escape(&email))));
+            originals.insert(alias.to_lowercase(), email.clone());
+            aliases.insert(email, alias);
+        }
+        for (name, alias) in names {
+            alternatives.push((name.len(), format!(r"\b{}\b", regex::escape(&name))));
+            originals.insert(alias.to_lowercase(), name.clone());
+            aliases.insert(name, alias);
+        }
+        // Longest first, so a full name wins over a first name contained in it
+        alternatives.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let pattern = (!alternatives.is_empty()).then(|| {
+            let alternatives: Vec<String> = alternatives.into_iter().map(|(_, a)| a).collect();
+            Regex::new(&alternatives.join("|")).unwrap()
+        });
+        let restore_pattern = (!originals.is_empty()).then(|| {
+            let mut aliases: Vec<&String> = originals.keys().collect();
+            aliases.sort_by_key(|a| std::cmp::Reverse(a.len()));
+            let aliases: Vec<String> = aliases.into_iter().map(|a| regex::escape(a)).collect();
+            Regex::new(&format!("(?i:{})", aliases.join("|"))).unwrap()
+        });
+
+        Pseudonymiser {
+            pattern,
+            aliases,
+            restore_pattern,
+            originals,
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let Some(ref pattern) = self.pattern else {
+            return text.to_string();
+        };
+        pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                let matched = &caps[0];
+                self.aliases
+                    .get(matched)
+                    .or_else(|| self.aliases.get(&matched.to_lowercase()))
+                    .cloned()
+                    .unwrap_or_else(|| matched.to_string())
+            })
+            .into_owned()
+    }
+
+    pub fn restore(&self, text: &str) -> String {
+        let Some(ref pattern) = self.restore_pattern else {
+            return text.to_string();
+        };
+        pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                self.originals
+                    .get(&caps[0].to_lowercase())
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    pub fn restore_structured(&self, summary: StructuredSummary) -> StructuredSummary {
+        StructuredSummary {
+            summary: self.restore(&summary.summary),
+            work_areas: summary
+                .work_areas
+                .iter()
+                .map(|area| self.restore(area))
+                .collect(),
+            notable_contributions: summary
+                .notable_contributions
+                .into_iter()
+                .map(|mut contribution| {
+                    contribution.description = self.restore(&contribution.description);
+                    contribution
+                })
+                .collect(),
+            ..summary
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(name: &str, email: &str) -> Identity {
+        Identity {
+            name: Some(name.to_string()),
+            email: Some(email.to_string()),
+        }
+    }
+
+    #[test]
+    fn alias_id_is_stable_and_unique() {
+        let empty = HashMap::new();
+        let id = alias_id("ada@example.com", &empty);
+        assert_eq!(id.len(), MIN_ALIAS_LENGTH);
+        assert_eq!(id, alias_id("ADA@example.com", &empty));
+        let hash = format!("{:x}", md5::compute("ada@example.com"));
+
+        let cases = [
+            // Already taken by the same key
+            (vec![(id.clone(), "ada@example.com")], 4),
+            // Taken by another key, so lengthened
+            (vec![(id.clone(), "bob@example.com")], 6),
+            (
+                vec![
+                    (id.clone(), "bob@example.com"),
+                    (hash[..6].to_string(), "carol@example.com"),
+                ],
+                8,
+            ),
+        ];
+        for (taken, expected_length) in cases {
+            let taken: HashMap<String, String> = taken
+                .into_iter()
+                .map(|(id, key)| (id, key.to_string()))
+                .collect();
+            let unique = alias_id("ada@example.com", &taken);
+            assert_eq!(unique.len(), expected_length, "taken {taken:?}");
+            assert_eq!(unique, hash[..expected_length]);
+        }
+    }
+
+    #[test]
+    fn colliding_aliases_restore_to_the_right_person() {
+        // Both emails' md5 start with 29e1, so the second one gets a longer alias
+        let pseudonymiser = Pseudonymiser::new(&[
+            identity("Sam Student", "student85@uni.edu"),
+            identity("Alex Learner", "student407@uni.edu"),
+        ]);
+        let text = "Sam Student <student85@uni.edu> reviewed the change by Alex Learner \
+                    <student407@uni.edu>";
+        let applied = pseudonymiser.apply(text);
+        assert_eq!(
+            applied,
+            "Person 29e1 <person-29e1@example.invalid> reviewed the change by Person 29e11e \
+             <person-29e11e@example.invalid>"
+        );
+        assert_eq!(pseudonymiser.restore(&applied), text);
+    }
+
+    #[test]
+    fn apply_and_restore() {
+        let pseudonymiser = Pseudonymiser::new(&[
+            identity("Ada Lovelace", "ada@example.com"),
+            identity("Ada", "ada2@example.com"),
+            identity("Al", "al@example.com"),
+        ]);
+        let cases = [
+            "Ada Lovelace fixed the parser",
+            "Mail ADA@example.com or ada2@example.com",
+            "Ada and Ada Lovelace paired",
+            // Too short to replace safely
+            "Al wrote tests",
+            "Nothing personal",
+        ];
+        for text in cases {
+            let applied = pseudonymiser.apply(text);
+            assert!(find_emails(&applied).is_empty(), "text {text:?}");
+            assert_eq!(
+                pseudonymiser.restore(&applied).to_lowercase(),
+                text.to_lowercase(),
+                "text {text:?}"
+            );
+        }
+        assert_eq!(pseudonymiser.apply("Al wrote tests"), "Al wrote tests");
+    }
+
+    #[test]
+    fn find_emails_skips_aliases() {
+        let cases = [
+            ("a@b.io and person-1234@example.invalid", vec!["a@b.io"]),
+            ("A.B+c@Example.COM", vec!["a.b+c@example.com"]),
+            ("no emails @ all", vec![]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(find_emails(text), expected, "text {text:?}");
+        }
+    }
+}
@@ -1,4 +1,16 @@
-use git2::{build::RepoBuilder, RemoteCallbacks};
+use git2::{build::RepoBuilder, RemoteCallbacks, Repository};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::credentials::get_git_token;
+use crate::forge_activity::ForgeClient;
+use crate::git_hosts::HostKind;
+use crate::url_verifier::{
+    get_clone_path, verify_and_extract_source_info, SourceKind, SourceKindArg,
+};
+use crate::utils::to_string;
 
 fn clone_progress(cur_progress: usize, total_progress: usize) {
     print!("\rProgress: {cur_progress}/{total_progress}");
@@ -19,8 +31,8 @@ pub fn try_clone_with_token(
         true
     });
 
-    // Set up authentication if token is provided
-    let token_owned = token.map(|t| t.to_string());
+    // Set up authentication if token is provided, otherwise use the stored token for the host
+    let token_owned = token.map(|t| t.to_string()).or_else(|| get_git_token(url));
     if let Some(ref access_token) = token_owned {
         let token_clone = access_token.clone();
         callbacks.credentials(move |_url, _username_from_url, _allowed_types| {
@@ -212,26 +224,222 @@ pub async fn refresh_repo(url: &str, path: &str, depth: Option<i32>) -> Result<(
     bare_clone(url, path, depth).await
 }
 
-// Function used to determine if the repository exists online or not
-// async fn check_repo_exists_online(url: &str) -> Result<bool, String> {
+// Archives often wrap the repository in a single top-level directory
+fn archive_repository_root(dir: &Path) -> Option<PathBuf> {
+    let mut candidates = vec![dir.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        let subdirectories: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        if let [only] = subdirectories.as_slice() {
+            candidates.push(only.clone());
+        }
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| Repository::open(candidate).is_ok())
+}
+
+/// Extracts a .zip export of a repository, which must include its .git directory, into
+/// `<data directory>/repositories/archive-<name>` and returns the repository root, which is
+/// then opened like a local repository. An earlier import of the same name is replaced.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_archive(path: &str) -> Result<String, String> {
+    let source =
+        verify_and_extract_source_info(path, Some(SourceKindArg::Kind(SourceKind::Archive)))?;
+    let target = get_clone_path(
+        SourceKindArg::Kind(SourceKind::Archive),
+        &source.owner,
+        &source.repo,
+    )
+    .await?;
+
+    if Path::new(&target).exists() {
+        log::info!("Replacing the earlier import at {target}");
+        std::fs::remove_dir_all(&target).map_err(to_string)?;
+    }
+    let file = File::open(path).map_err(|e| format!("Failed to open archive: {e}"))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid archive: {e}"))?;
+    // Entries with absolute paths or `..` components are rejected rather than written outside
+    archive
+        .extract(&target)
+        .map_err(|e| format!("Failed to extract archive: {e}"))?;
+
+    match archive_repository_root(Path::new(&target)) {
+        Some(root) => {
+            log::info!("Imported {path} to {}", root.display());
+            Ok(root.to_string_lossy().to_string())
+        }
+        None => {
+            if let Err(e) = std::fs::remove_dir_all(&target) {
+                log::warn!("Failed to remove {target}: {e}");
+            }
+            Err(format!(
+                "'{path}' doesn't contain a git repository. Archives need the .git directory."
+            ))
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    Public,
+    Private,
+    /// Visible to anyone signed in to the host (GitLab and GitHub Enterprise).
+    Internal,
+}
+
+/// What the host's API says about a repository before cloning it.
+#[derive(Serialize, Debug)]
+pub struct RepoPreflight {
+    /// False when the host reports no such repository. Without a token this is also what a
+    /// private repository looks like, so `token_required` is set as well.
+    pub exists: bool,
+    pub token_required: bool,
+    pub visibility: Option<Visibility>,
+    pub default_branch: Option<String>,
+    pub size_kb: Option<u64>,
+    /// RFC 3339 time of the last push (GitHub) or activity (GitLab).
+    pub last_push: Option<String>,
+    pub large: bool,
+    /// A clone depth to suggest for large repositories.
+    pub suggested_depth: Option<i32>,
+}
+
+// Repositories larger than this are slow to clone in full
+const LARGE_REPO_KB: u64 = 500 * 1024;
+const LARGE_REPO_DEPTH: i32 = 100;
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    private: bool,
+    visibility: Option<String>,
+    default_branch: Option<String>,
+    size: Option<u64>,
+    pushed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabStatistics {
+    repository_size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    visibility: Option<String>,
+    default_branch: Option<String>,
+    last_activity_at: Option<String>,
+    statistics: Option<GitLabStatistics>,
+}
+
+fn parse_visibility(visibility: Option<&str>) -> Option<Visibility> {
+    match visibility? {
+        "public" => Some(Visibility::Public),
+        "private" => Some(Visibility::Private),
+        "internal" => Some(Visibility::Internal),
+        _ => None,
+    }
+}
 
-//     // Set-up reqwest client
-//     let client = reqwest::Client::builder()
-//         .timeout(Duration::from_secs(30)) //Set a timeout of 30 seconds
-//         .connect_timeout(Duration::from_secs(10))// Set a connection timeout of 10 seconds
-//         .build()
-//         .map_err(|e| format!("Failed to build client: {}", e))?;
+fn found(
+    visibility: Option<Visibility>,
+    default_branch: Option<String>,
+    size_kb: Option<u64>,
+    last_push: Option<String>,
+) -> RepoPreflight {
+    let large = size_kb.is_some_and(|size| size > LARGE_REPO_KB);
+    RepoPreflight {
+        exists: true,
+        token_required: visibility.is_some_and(|v| v != Visibility::Public),
+        visibility,
+        default_branch,
+        size_kb,
+        last_push,
+        large,
+        suggested_depth: large.then_some(LARGE_REPO_DEPTH),
+    }
+}
 
-//     let result = client.get(url)
-//         .send()
-//         .await
-//         .map_err(|e| format!("Request failed: {}", e))?;
+/// Asks the GitHub or GitLab API whether the repository at `url` exists, whether it is private,
+/// its default branch, size and last push, so the UI can ask for a token or suggest a clone
+/// depth before cloning. `token` defaults to the stored token for the host.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn preflight_remote_repository(
+    url: &str,
+    token: Option<&str>,
+) -> Result<RepoPreflight, String> {
+    let client = ForgeClient::new(url, token)?;
+    let (owner, repo) = (&client.remote.owner, &client.remote.repo);
+    let response = match client.kind() {
+        HostKind::GitHub => {
+            client
+                .get_optional(&format!("/repos/{owner}/{repo}"), &[])
+                .await?
+        }
+        HostKind::GitLab => {
+            let project: String =
+                url::form_urlencoded::byte_serialize(format!("{owner}/{repo}").as_bytes())
+                    .collect();
+            client
+                .get_optional(
+                    &format!("/projects/{project}"),
+                    &[("statistics", "true".to_string())],
+                )
+                .await?
+        }
+        kind => {
+            return Err(format!(
+                "Preflight checks aren't supported for {kind:?} hosts"
+            ))
+        }
+    };
 
-//     log::info!("Received status code: {}", result.status());
-//     match result.status() {
-//         reqwest::StatusCode::OK | reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::UNAUTHORIZED => Ok(true),
-//         reqwest::StatusCode::NOT_FOUND => Ok(false),
-//         status => Err(format!("Unexpected status code: {}", status)),
-//     }
+    // Both hosts hide private repositories from requests that can't see them
+    let Some(response) = response else {
+        return Ok(RepoPreflight {
+            exists: false,
+            token_required: !client.has_token(),
+            visibility: None,
+            default_branch: None,
+            size_kb: None,
+            last_push: None,
+            large: false,
+            suggested_depth: None,
+        });
+    };
 
-// }
+    match client.kind() {
+        HostKind::GitHub => {
+            let repo: GitHubRepo = serde_json::from_value(response).map_err(to_string)?;
+            let visibility =
+                parse_visibility(repo.visibility.as_deref()).or(Some(if repo.private {
+                    Visibility::Private
+                } else {
+                    Visibility::Public
+                }));
+            Ok(found(
+                visibility,
+                repo.default_branch,
+                repo.size,
+                repo.pushed_at,
+            ))
+        }
+        _ => {
+            let project: GitLabProject = serde_json::from_value(response).map_err(to_string)?;
+            // Statistics are only returned to project members, and are in bytes
+            let size_kb = project
+                .statistics
+                .and_then(|s| s.repository_size)
+                .map(|bytes| bytes / 1024);
+            Ok(found(
+                parse_visibility(project.visibility.as_deref()),
+                project.default_branch,
+                size_kb,
+                project.last_activity_at,
+            ))
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use dirs::{config_dir, data_dir};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::git_hosts::GitHost;
+use crate::llm::{GenerationOptions, LlmConfig};
+use crate::summary_input::SummaryInputOptions;
+use crate::utils::to_string;
+
+/*
+settings.json lives in the OS config directory (not the data directory, since it is what
+points at the data directory) and is this format
+{
+    "data_directory": "<path>" | Null,
+    "llm": { "provider": ..., "models": [...] } (See llm.rs),
+    "generation": { "max_concurrent": <n>, "requests_per_minute": <n>, ... } (See llm.rs),
+    "summary_input": { "token_budget": <tokens>, ... } (See summary_input.rs),
+    "prompt_template": "<template name>" | Null (See prompt_templates.rs)
+    "git_hosts": [<host>] (See git_hosts.rs)
+}
+    */
+
+pub const DATA_DIR_ENV_VAR: &str = "GITGAUGE_DATA_DIR";
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub data_directory: Option<String>,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub generation: GenerationOptions,
+    #[serde(default)]
+    pub summary_input: SummaryInputOptions,
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+    #[serde(default)]
+    pub git_hosts: Vec<GitHost>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirectorySource {
+    Flag,
+    Environment,
+    Settings,
+    Default,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DataDirectoryInfo {
+    pub path: String,
+    pub source: DataDirectorySource,
+    pub settings_path: String,
+}
+
+// Resolved once at startup and updated when the data directory is moved
+static DATA_DIRECTORY: RwLock<Option<(PathBuf, DataDirectorySource)>> = RwLock::new(None);
+
+pub fn get_settings_path() -> PathBuf {
+    let mut path = config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("gitgauge");
+    path.push("settings.json");
+    path
+}
+
+pub fn read_settings() -> Settings {
+    let path = get_settings_path();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid settings file {path:?}: {e}");
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+pub fn write_settings(settings: &Settings) -> Result<(), String> {
+    let path = get_settings_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(to_string)?;
+    }
+    std::fs::write(
+        path,
+        serde_json::to_string_pretty(settings).map_err(to_string)?,
+    )
+    .map_err(to_string)
+}
+
+fn default_data_directory() -> PathBuf {
+    let mut path = data_dir().unwrap_or_else(|| PathBuf::from(".")); // Fallback to current dir if data_dir fails
+    path.push("gitgauge");
+    path
+}
+
+// Accepts both `--data-dir <path>` and `--data-dir=<path>`
+fn parse_data_dir_flag(args: &[String]) -> Option<PathBuf> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == DATA_DIR_FLAG {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{DATA_DIR_FLAG}=")) {
+            return Some(PathBuf::from(value));
+        }
+    }
+    None
+}
+
+fn resolve_data_directory(args: &[String]) -> (PathBuf, DataDirectorySource) {
+    if let Some(path) = parse_data_dir_flag(args) {
+        return (path, DataDirectorySource::Flag);
+    }
+    if let Ok(path) = std::env::var(DATA_DIR_ENV_VAR) {
+        if !path.trim().is_empty() {
+            return (PathBuf::from(path), DataDirectorySource::Environment);
+        }
+    }
+    if let Some(path) = read_settings().data_directory {
+        return (PathBuf::from(path), DataDirectorySource::Settings);
+    }
+    (default_data_directory(), DataDirectorySource::Default)
+}
+
+/// Resolves the data directory from the command-line flag, the `GITGAUGE_DATA_DIR`
+/// environment variable, the settings file, then `data_dir()/gitgauge`, in that order.
+/// Returns the directory and where it came from, to be logged once the logger is set up.
+pub fn init_data_directory(args: &[String]) -> (PathBuf, DataDirectorySource) {
+    let resolved = resolve_data_directory(args);
+    if let Ok(mut data_directory) = DATA_DIRECTORY.write() {
+        *data_directory = Some(resolved.clone());
+    }
+    resolved
+}
+
+fn current_data_directory() -> (PathBuf, DataDirectorySource) {
+    if let Ok(data_directory) = DATA_DIRECTORY.read() {
+        if let Some(current) = data_directory.as_ref() {
+            return current.clone();
+        }
+    }
+    resolve_data_directory(&[])
+}
+
+pub fn data_directory() -> PathBuf {
+    current_data_directory().0
+}
+
+fn data_directory_info() -> DataDirectoryInfo {
+    let (path, source) = current_data_directory();
+    DataDirectoryInfo {
+        path: path.to_string_lossy().to_string(),
+        source,
+        settings_path: get_settings_path().to_string_lossy().to_string(),
+    }
+}
+
+#[tauri::command]
+pub fn get_data_directory_info() -> DataDirectoryInfo {
+    data_directory_info()
+}
+
+fn copy_recursive(src: &Path, dst: &Path) -> Result<(), String> {
+    if src.is_dir() {
+        std::fs::create_dir_all(dst).map_err(to_string)?;
+        for entry in std::fs::read_dir(src).map_err(to_string)? {
+            let entry = entry.map_err(to_string)?;
+            copy_recursive(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(src, dst).map_err(to_string)?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Where `path` is after the data directory moved from `old_dir` to `new_dir`. Local
+/// repositories outside the data directory don't move, so they give None.
+pub fn moved_path(path: &str, old_dir: &Path, new_dir: &Path) -> Option<String> {
+    let relative = Path::new(path).strip_prefix(old_dir).ok()?;
+    Some(new_dir.join(relative).to_string_lossy().to_string())
+}
+
+fn rewrite_repository_paths(repos: &mut [Value], old_dir: &Path, new_dir: &Path) {
+    for repo in repos.iter_mut() {
+        let Some(path) = repo.get("path").and_then(|p| p.as_str()) else {
+            continue;
+        };
+        if let Some(new_path) = moved_path(path, old_dir, new_dir) {
+            repo["path"] = Value::String(new_path);
+        }
+    }
+}
+
+fn rewrite_manifest_paths(manifest: &mut Value, old_dir: &Path, new_dir: &Path) {
+    if let Some(Value::Array(repos)) = manifest.get_mut("repository") {
+        rewrite_repository_paths(repos, old_dir, new_dir);
+    }
+    if let Some(Value::Array(workspaces)) = manifest.get_mut("workspaces") {
+        for workspace in workspaces.iter_mut() {
+            if let Some(Value::Array(repos)) = workspace.get_mut("repository") {
+                rewrite_repository_paths(repos, old_dir, new_dir);
+            }
+        }
+    }
+}
+
+/// Moves the manifest, clones and caches to `new_path`, rewrites the stored repository
+/// paths in the manifest and summary cache and remembers the new location in the settings file. Everything is copied before
+/// anything is deleted, so a failed move leaves the old data directory intact.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn move_data_directory(new_path: &str) -> Result<DataDirectoryInfo, String> {
+    let old_dir = data_directory();
+    let new_dir = PathBuf::from(new_path);
+
+    if new_path.trim().is_empty() {
+        return Err("New data directory cannot be empty".to_string());
+    }
+    if new_dir == old_dir {
+        return Ok(data_directory_info());
+    }
+    if new_dir.starts_with(&old_dir) {
+        return Err("New data directory cannot be inside the current one".to_string());
+    }
+    if new_dir.join("manifest.json").exists() {
+        return Err(format!(
+            "{new_path} already contains a GitGauge manifest. Choose an empty directory."
+        ));
+    }
+
+    std::fs::create_dir_all(&new_dir)
+        .map_err(|e| format!("Failed to create data directory {new_path}: {e}"))?;
+
+    // On macOS and Windows the config and data directories coincide, so leave the
+    // settings file where it is
+    let settings_path = get_settings_path();
+    let entries: Vec<PathBuf> = match std::fs::read_dir(&old_dir) {
+        Ok(read_dir) => read_dir
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| *path != settings_path)
+            .collect(),
+        Err(_) => Vec::new(), // Nothing to move yet
+    };
+
+    let mut copied: Vec<PathBuf> = Vec::new();
+    for entry in &entries {
+        let Some(name) = entry.file_name() else {
+            continue;
+        };
+        let target = new_dir.join(name);
+        if let Err(e) = copy_recursive(entry, &target) {
+            log::error!("Failed to copy {entry:?} to {target:?}: {e}");
+            for path in copied.iter().chain(std::iter::once(&target)) {
+                let _ = remove_path(path);
+            }
+            return Err(format!("Failed to move data directory: {e}"));
+        }
+        copied.push(target);
+    }
+
+    let manifest_path = new_dir.join("manifest.json");
+    if manifest_path.exists() {
+        let content = std::fs::read_to_string(&manifest_path).map_err(to_string)?;
+        let mut manifest: Value = serde_json::from_str(&content).map_err(to_string)?;
+        rewrite_manifest_paths(&mut manifest, &old_dir, &new_dir);
+        std::fs::write(
+            &manifest_path,
+            serde_json::to_string_pretty(&manifest).map_err(to_string)?,
+        )
+        .map_err(to_string)?;
+    }
+
+    let mut settings = read_settings();
+    settings.data_directory = Some(new_dir.to_string_lossy().to_string());
+    write_settings(&settings)?;
+
+    let (_, source) = current_data_directory();
+    if matches!(
+        source,
+        DataDirectorySource::Flag | DataDirectorySource::Environment
+    ) {
+        log::warn!("Data directory is set by {source:?}, which will take precedence on next start");
+    }
+    if let Ok(mut data_directory) = DATA_DIRECTORY.write() {
+        *data_directory = Some((new_dir.clone(), DataDirectorySource::Settings));
+    }
+    // Bundles find cached summaries by repository path
+    crate::summary_cache::move_repo_paths(&old_dir, &new_dir).await;
+
+    for entry in &entries {
+        if let Err(e) = remove_path(entry) {
+            log::warn!("Failed to remove old data {entry:?}: {e}");
+        }
+    }
+
+    log::info!("Moved data directory from {old_dir:?} to {new_dir:?}");
+    Ok(data_directory_info())
+}
@@ -0,0 +1,162 @@
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+
+use crate::llm;
+
+pub const STRUCTURED_SUMMARY_PROMPT: &str = include_str!("AI-structured-summary-prompt.md");
+
+// Attempts after the first when the model returns JSON that doesn't match the schema
+const MAX_PARSE_RETRIES: usize = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WorkSplit {
+    pub feature: u32,
+    pub bugfix: u32,
+    pub test: u32,
+    pub docs: u32,
+    pub refactor: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotableContribution {
+    pub description: String,
+    pub commits: Vec<String>,
+}
+
+/// Summary broken down so it can be justified commit by commit. Commit ids are full OIDs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StructuredSummary {
+    pub summary: String,
+    pub work_areas: Vec<String>,
+    pub work_split: WorkSplit,
+    pub notable_contributions: Vec<NotableContribution>,
+    pub evidence_commits: Vec<String>,
+}
+
+// Models like to wrap JSON in ```json fences even when asked not to
+pub fn extract_json(text: &str) -> &str {
+    let text = text.trim();
+    match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    }
+}
+
+// Resolves the (usually abbreviated) ids the model returned to the contributor's commits,
+// dropping any it made up
+fn resolve_commit_ids(ids: &[String], oids: &[Oid]) -> Vec<String> {
+    let mut resolved: Vec<String> = Vec::new();
+    for id in ids {
+        let id = id.trim().to_lowercase();
+        if id.len() < 4 {
+            continue;
+        }
+        match oids.iter().find(|oid| oid.to_string().starts_with(&id)) {
+            Some(oid) => {
+                let full = oid.to_string();
+                if !resolved.contains(&full) {
+                    resolved.push(full);
+                }
+            }
+            None => log::warn!("Dropping unknown commit id {id} from structured summary"),
+        }
+    }
+    resolved
+}
+
+/// Parses the model output and checks it against the schema in the prompt.
+pub fn parse_and_validate(text: &str, oids: &[Oid]) -> Result<StructuredSummary, String> {
+    let mut parsed: StructuredSummary =
+        serde_json::from_str(extract_json(text)).map_err(|e| format!("Invalid JSON: {e}"))?;
+
+    if parsed.summary.trim().is_empty() {
+        return Err("\"summary\" must not be empty".to_string());
+    }
+    // Some slack over the 30 words asked for, so a slightly long summary isn't regenerated
+    if parsed.summary.split_whitespace().count() > 40 {
+        return Err("\"summary\" must be at most 30 words".to_string());
+    }
+    if parsed.work_areas.is_empty() || parsed.work_areas.len() > 5 {
+        return Err("\"work_areas\" must have between 1 and 5 entries".to_string());
+    }
+
+    let split = &parsed.work_split;
+    // Widened, as the model can return values that overflow a u32 sum
+    let total: u64 = [
+        split.feature,
+        split.bugfix,
+        split.test,
+        split.docs,
+        split.refactor,
+    ]
+    .into_iter()
+    .map(u64::from)
+    .sum();
+    if !(95..=105).contains(&total) {
+        return Err(format!(
+            "\"work_split\" percentages must add up to 100, got {total}"
+        ));
+    }
+
+    for contribution in parsed.notable_contributions.iter_mut() {
+        contribution.commits = resolve_commit_ids(&contribution.commits, oids);
+    }
+    parsed.evidence_commits = resolve_commit_ids(&parsed.evidence_commits, oids);
+    if parsed.evidence_commits.is_empty() {
+        return Err(
+            "\"evidence_commits\" must contain ids of commits from the commit history".to_string(),
+        );
+    }
+
+    Ok(parsed)
+}
+
+/// Asks the configured provider for a structured summary using `prompt` (rendered from
+/// `STRUCTURED_SUMMARY_PROMPT` with the commits in `oids`), retrying with the validation
+/// error when the response doesn't match.
+pub async fn summarize_structured(
+    prompt: &str,
+    oids: &[Oid],
+    limiter: &llm::RateLimiter,
+) -> Result<StructuredSummary, String> {
+    let config = llm::get_config();
+    if !llm::provider_has_credentials(&config) {
+        return Err("No API key set for the configured AI provider".to_string());
+    }
+    generate_validated(prompt, limiter, "structured summary", |text| {
+        parse_and_validate(text, oids)
+    })
+    .await
+}
+
+/// Generates a response to `prompt` until `parse` accepts it, telling the model why the
+/// previous response was rejected. `output` names what is generated in logs and errors.
+pub async fn generate_validated<T>(
+    prompt: &str,
+    limiter: &llm::RateLimiter,
+    output: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    let config = llm::get_config();
+    let mut feedback = String::new();
+    let mut last_error = String::new();
+
+    for attempt in 0..=MAX_PARSE_RETRIES {
+        let text = llm::generate(&config, &format!("{prompt}{feedback}"), limiter).await?;
+        match parse(&text) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => {
+                log::warn!("Attempt {} at the {output} was invalid: {e}", attempt + 1);
+                feedback = format!(
+                    "\n\nYour previous response was rejected: {e}. Respond with only the JSON object described above."
+                );
+                last_error = e;
+            }
+        }
+    }
+
+    Err(format!("Failed to generate a valid {output}: {last_error}"))
+}
@@ -1,44 +1,327 @@
-use git2::{Repository, Sort};
-use serde::Deserialize;
-use serde_json::{json, Value};
-use std::collections::{HashMap, HashSet};
-use std::env;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use git2::{Oid, Repository, Sort};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tauri::{Emitter, State};
-
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::contributor::{resolve_branch_head, DateRange};
+use crate::credentials::{
+    forget_session_secret, remember_secret, validate_secret, CredentialKind, GEMINI_CREDENTIAL,
+};
+use crate::llm;
+use crate::offline_summary;
+use crate::prompt_templates::{self, describe_date_range, repo_name, PromptVariables};
+use crate::pseudonymise::{
+    collect_identities, find_emails, find_identities, Identity, Pseudonymiser,
+};
+use crate::structured_summary::{
+    summarize_structured, StructuredSummary, STRUCTURED_SUMMARY_PROMPT,
+};
+use crate::summary_cache;
+use crate::summary_input::{
+    self, build_summary_input, estimate_tokens, SummaryInput, SummaryInputOptions,
+};
 use crate::utils::to_string;
 
 #[derive(Clone, serde::Serialize)]
 struct SummaryProgress {
+    job_id: String,
     email: String,
     summary: String,
+    // Only set when summaries are generated in structured mode
+    structured: Option<StructuredSummary>,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct SummaryTotal {
+    job_id: String,
+    total: usize,
+}
+
+pub struct SummaryResult {
+    pub summary: String,
+    pub structured: Option<StructuredSummary>,
+}
+
+/// The branch and date range applied by `get_contributor_info`, so that summaries describe the
+/// same work as the stats shown next to them.
+///
+/// The regex is stricter here than on the dashboard. `get_contributor_info` counts every commit
+/// and only reports how many match (`commits_matching_regex`), whereas a summary only covers the
+/// matching commits, so with a regex it corresponds to that count rather than `total_commits`.
+pub struct CommitFilter {
+    pub branch: Option<String>,
+    pub date_range: Option<DateRange>,
+    pub regex: Option<Regex>,
+}
+
+impl CommitFilter {
+    pub fn new(
+        branch: Option<&str>,
+        date_range: Option<DateRange>,
+        regex_query: Option<&str>,
+    ) -> Result<Self, String> {
+        let regex = regex_query
+            .filter(|q| !q.is_empty())
+            .map(Regex::new)
+            .transpose()
+            .map_err(to_string)?;
+
+        Ok(CommitFilter {
+            branch: branch.map(String::from),
+            date_range,
+            regex,
+        })
+    }
+
+    /// Commits on the branch (or HEAD) within the date range whose message matches the
+    /// regex, newest first.
+    pub fn walk(&self, repo: &Repository) -> Result<Vec<Oid>, String> {
+        let mut revwalk = repo.revwalk().map_err(to_string)?;
+        revwalk
+            .push(resolve_branch_head(repo, self.branch.as_deref())?)
+            .map_err(to_string)?;
+        revwalk.set_sorting(Sort::TIME).map_err(to_string)?;
+
+        let mut oids = Vec::new();
+        for oid in revwalk {
+            let oid = oid.map_err(to_string)?;
+            let commit = repo.find_commit(oid).map_err(to_string)?;
+
+            if let Some(ref date_range) = self.date_range {
+                if !date_range.contains(commit.time().seconds()) {
+                    continue;
+                }
+            }
+            if let Some(ref regex) = self.regex {
+                if !regex.is_match(commit.message_raw().unwrap_or("")) {
+                    continue;
+                }
+            }
+            oids.push(oid);
+        }
+        Ok(oids)
+    }
 }
 
-// Global state for cancellation
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+const CANCELLED: &str = "Summary generation cancelled";
+
+/// Cancellation tokens of the summary jobs in progress, by job ID, so several jobs (e.g. from
+/// different windows) can run and be cancelled independently.
+#[derive(Default)]
 pub struct CancellationState {
-    pub cancelled: Arc<AtomicBool>,
+    jobs: Mutex<HashMap<String, CancellationToken>>,
 }
 
-impl Default for CancellationState {
-    fn default() -> Self {
-        Self {
-            cancelled: Arc::new(AtomicBool::new(false)),
+/// A registered job. Dropping it unregisters the job.
+pub struct RunningJob<'a> {
+    state: &'a CancellationState,
+    pub id: String,
+    pub token: CancellationToken,
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.state.jobs.lock() {
+            jobs.remove(&self.id);
+        }
+    }
+}
+
+impl CancellationState {
+    /// Registers a job under `job_id`, or a generated ID if none is given.
+    pub fn start(&self, job_id: Option<String>) -> Result<RunningJob<'_>, String> {
+        let id = job_id
+            .unwrap_or_else(|| format!("summary-{}", NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)));
+        let mut jobs = self.jobs.lock().map_err(to_string)?;
+        if jobs.contains_key(&id) {
+            return Err(format!("Summary job {id} is already running"));
+        }
+        let token = CancellationToken::new();
+        jobs.insert(id.clone(), token.clone());
+        log::info!("Started summary job {id}");
+        Ok(RunningJob {
+            state: self,
+            id,
+            token,
+        })
+    }
+
+    /// Cancels the job with `job_id`, or every job if none is given. Returns how many jobs
+    /// were cancelled.
+    pub fn cancel(&self, job_id: Option<&str>) -> usize {
+        let Ok(jobs) = self.jobs.lock() else {
+            return 0;
+        };
+        let mut cancelled = 0;
+        for (id, token) in jobs.iter() {
+            if job_id.is_none_or(|job_id| job_id == id) {
+                token.cancel();
+                cancelled += 1;
+            }
         }
+        cancelled
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+struct SummaryJobStarted {
+    job_id: String,
+}
+
+// Carries the job ID, as every window receives the events of every job
+fn emit_total(window: &tauri::Window, job: &RunningJob, total: usize) {
+    let payload = SummaryTotal {
+        job_id: job.id.clone(),
+        total,
+    };
+    if let Err(e) = window.emit("summary-total", payload) {
+        log::error!("Failed to emit summary total: {e}");
+    }
+}
+
+/// Registers the job and tells the frontend its ID, which it needs to cancel a job it
+/// didn't name itself.
+pub fn start_job<'a>(
+    window: &tauri::Window,
+    state: &'a CancellationState,
+    job_id: Option<String>,
+) -> Result<RunningJob<'a>, String> {
+    let job = state.start(job_id)?;
+    let started = SummaryJobStarted {
+        job_id: job.id.clone(),
+    };
+    if let Err(e) = window.emit("summary-job-started", started) {
+        log::error!("Failed to emit summary job start: {e}");
+    }
+    Ok(job)
+}
+
+/// Runs `future` until it completes or the job is cancelled. Cancelling drops the future,
+/// which aborts any request it has in flight.
+pub async fn until_cancelled<T>(
+    token: &CancellationToken,
+    future: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    tokio::select! {
+        _ = token.cancelled() => Err(CANCELLED.to_string()),
+        result = future => result,
+    }
+}
+
+/// One contributor (or mapped user) whose commits are summarised together.
+struct SummaryJob {
+    // Contributor name, or the user name from the email mapping
+    contributor: String,
+    emails: Vec<String>,
+    commits: Vec<Oid>,
+    force: bool,
+}
+
+/// Summarises the jobs concurrently (up to the configured limit), emitting `summary-progress`
+/// for each email of a job as soon as its summary is ready.
+async fn run_summary_jobs(
+    window: &tauri::Window,
+    path: &str,
+    jobs: Vec<SummaryJob>,
+    date_range: Option<&DateRange>,
+    structured: bool,
+    identities: Option<Arc<Vec<Identity>>>,
+    running_job: &RunningJob<'_>,
+) -> Result<(), String> {
+    let date_range = describe_date_range(date_range);
+    let options = llm::get_generation_options();
+    let limiter = Arc::new(llm::RateLimiter::new(
+        options.requests_per_minute,
+        options.max_concurrent,
+    ));
+    let semaphore = Arc::new(Semaphore::new(options.max_concurrent.max(1)));
+    let mut tasks = JoinSet::new();
+
+    for job in jobs {
+        let window = window.clone();
+        let context = SummaryContext {
+            repo_path: path.to_string(),
+            contributor: job.contributor.clone(),
+            date_range: date_range.clone(),
+            identities: identities.clone(),
+        };
+        let limiter = limiter.clone();
+        let semaphore = semaphore.clone();
+        let job_id = running_job.id.clone();
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.map_err(to_string)?;
+
+            let result =
+                summarize_with_cache(&context, &job.commits, job.force, structured, &limiter)
+                    .await?;
+
+            // Send progress for each email associated with this contributor
+            for email in job.emails {
+                let progress = SummaryProgress {
+                    job_id: job_id.clone(),
+                    email,
+                    summary: result.summary.clone(),
+                    structured: result.structured.clone(),
+                };
+                if let Err(e) = window.emit("summary-progress", progress) {
+                    log::error!("Failed to emit summary progress: {e}");
+                }
+            }
+            Ok(())
+        });
+    }
+
+    loop {
+        // Aborting the tasks drops their requests mid-flight rather than waiting for them
+        let result = tokio::select! {
+            _ = running_job.token.cancelled() => {
+                tasks.abort_all();
+                log::info!("Summary job {} cancelled by user", running_job.id);
+                return Err(CANCELLED.to_string());
+            }
+            result = tasks.join_next() => result,
+        };
+        let error = match result {
+            None => return Ok(()),
+            Some(Ok(Ok(()))) => continue,
+            Some(Ok(Err(e))) => e,
+            Some(Err(e)) => e.to_string(),
+        };
+        tasks.abort_all();
+        return Err(error);
     }
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_ai_summary(
     window: tauri::Window,
     path: &str,
+    branch: Option<&str>,
+    date_range: Option<DateRange>,
+    regex_query: Option<&str>,
+    force_regenerate: Option<Vec<String>>,
+    structured: Option<bool>,
+    pseudonymise: Option<bool>,
+    job_id: Option<String>,
     cancellation_state: State<'_, CancellationState>,
 ) -> Result<(), String> {
-    // Reset cancellation flag at the start
-    cancellation_state.cancelled.store(false, Ordering::SeqCst);
+    let job = start_job(&window, &cancellation_state, job_id)?;
+    let filter = CommitFilter::new(branch, date_range, regex_query)?;
+    let force_regenerate = force_regenerate.unwrap_or_default();
+    let structured = structured.unwrap_or(false);
 
-    match get_all_contributors(path) {
+    match get_all_contributors(path, &filter) {
         Ok(contributors) => {
             let total = contributors.len();
 
@@ -48,35 +331,37 @@ pub async fn get_ai_summary(
                 return Err(msg);
             }
 
-            window.emit("summary-total", total).unwrap();
+            emit_total(&window, &job, total);
 
+            let mut jobs = Vec::new();
             for (contributor_name, contributor_email) in contributors {
-                // Check if cancelled
-                if cancellation_state.cancelled.load(Ordering::SeqCst) {
-                    log::info!("Summary generation cancelled by user");
-                    return Err("Summary generation cancelled".to_string());
-                }
-
-                if let Ok(commits) = get_contributor_commits(path, &contributor_name) {
+                if let Ok(commits) = get_contributor_commits(path, &contributor_name, &filter) {
                     if !commits.is_empty() {
-                        match summarize_commits(&commits).await {
-                            Ok(summary) => {
-                                let progress = SummaryProgress {
-                                    email: contributor_email.clone(),
-                                    summary,
-                                };
-
-                                window.emit("summary-progress", progress).unwrap();
-                            }
-                            Err(e) => {
-                                let err_msg = e.to_string();
-                                Err(err_msg)?
-                            }
-                        }
+                        jobs.push(SummaryJob {
+                            contributor: contributor_name,
+                            force: force_regenerate.contains(&contributor_email),
+                            emails: vec![contributor_email],
+                            commits,
+                        });
                     }
                 }
             }
-            Ok(())
+
+            let identities = if pseudonymise.unwrap_or(false) {
+                Some(get_job_identities(path, &jobs)?)
+            } else {
+                None
+            };
+            run_summary_jobs(
+                &window,
+                path,
+                jobs,
+                filter.date_range.as_ref(),
+                structured,
+                identities,
+                &job,
+            )
+            .await
         }
         Err(e) => {
             let msg = format!("Failed to get contributors for path {path}: {e}");
@@ -86,277 +371,474 @@ pub async fn get_ai_summary(
     }
 }
 
+/// One job per user in `config_json` with commits selected by `filter`.
+async fn get_config_jobs(
+    path: &str,
+    config_json: Value,
+    filter: &CommitFilter,
+    force_regenerate: &[String],
+) -> Result<Vec<SummaryJob>, String> {
+    let squashed_commits = get_squashed_commits_by_config(path, config_json.clone(), filter)
+        .await
+        .map_err(|e| {
+            let msg = format!("Failed to get squashed commits for path {path}: {e}");
+            log::error!("{msg}");
+            msg
+        })?;
+
+    // Get email mapping from config
+    let mut user_to_emails: HashMap<String, Vec<String>> = HashMap::new();
+    if let Value::Object(ref map) = config_json {
+        for (user_name, emails_value) in map.iter() {
+            if let Value::Array(email_list) = emails_value {
+                let emails: Vec<String> = email_list
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect();
+                user_to_emails.insert(user_name.clone(), emails);
+            }
+        }
+    }
+
+    let mut jobs = Vec::new();
+    for (user_name, commit_data) in squashed_commits {
+        if !commit_data.is_empty() {
+            let emails = user_to_emails.remove(&user_name).unwrap_or_default();
+            // Contributors can be forced by mapped name or by any of their emails
+            let force = force_regenerate.contains(&user_name)
+                || emails.iter().any(|e| force_regenerate.contains(e));
+            jobs.push(SummaryJob {
+                contributor: user_name,
+                emails,
+                commits: commit_data,
+                force,
+            });
+        }
+    }
+
+    if jobs.is_empty() {
+        let msg = format!("No contributors found in config for repository at path: {path}");
+        log::error!("{msg}");
+        return Err(msg);
+    }
+    Ok(jobs)
+}
+
+// Everyone who could be named in the jobs' prompts: commit authors and committers, and the
+// contributor names and emails of the jobs themselves
+fn get_job_identities(path: &str, jobs: &[SummaryJob]) -> Result<Arc<Vec<Identity>>, String> {
+    let oids: Vec<Oid> = jobs.iter().flat_map(|job| job.commits.clone()).collect();
+    let mut identities = collect_identities(path, &oids)?;
+    for job in jobs {
+        identities.push(Identity {
+            name: Some(job.contributor.clone()),
+            email: None,
+        });
+        identities.extend(job.emails.iter().map(|email| Identity {
+            name: None,
+            email: Some(email.to_lowercase()),
+        }));
+    }
+    Ok(Arc::new(identities))
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_ai_summary_with_config(
     window: tauri::Window,
     path: &str,
     config_json: Value,
+    branch: Option<&str>,
+    date_range: Option<DateRange>,
+    regex_query: Option<&str>,
+    force_regenerate: Option<Vec<String>>,
+    structured: Option<bool>,
+    pseudonymise: Option<bool>,
+    job_id: Option<String>,
     cancellation_state: State<'_, CancellationState>,
 ) -> Result<(), String> {
-    // Reset cancellation flag at the start
-    cancellation_state.cancelled.store(false, Ordering::SeqCst);
-
-    match get_squashed_commits_by_config(path, config_json.clone()).await {
-        Ok(squashed_commits) => {
-            let total = squashed_commits.len();
-
-            if total == 0 {
-                let msg = format!("No contributors found in config for repository at path: {path}");
-                log::error!("{msg}");
-                return Err(msg);
-            }
+    let job = start_job(&window, &cancellation_state, job_id)?;
+    let filter = CommitFilter::new(branch, date_range, regex_query)?;
+    let force_regenerate = force_regenerate.unwrap_or_default();
+
+    let jobs = get_config_jobs(path, config_json, &filter, &force_regenerate).await?;
+    emit_total(&window, &job, jobs.len());
+
+    let identities = if pseudonymise.unwrap_or(false) {
+        Some(get_job_identities(path, &jobs)?)
+    } else {
+        None
+    };
+    run_summary_jobs(
+        &window,
+        path,
+        jobs,
+        filter.date_range.as_ref(),
+        structured.unwrap_or(false),
+        identities,
+        &job,
+    )
+    .await
+}
 
-            window.emit("summary-total", total).unwrap();
-
-            // Get email mapping from config
-            let mut user_to_emails: HashMap<String, Vec<String>> = HashMap::new();
-            if let Value::Object(ref map) = config_json {
-                for (user_name, emails_value) in map.iter() {
-                    if let Value::Array(email_list) = emails_value {
-                        let emails: Vec<String> = email_list
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
-                        user_to_emails.insert(user_name.clone(), emails);
-                    }
-                }
-            }
+// Rough response sizes, for the cost estimate
+const SUMMARY_OUTPUT_TOKENS: usize = 60;
+const STRUCTURED_OUTPUT_TOKENS: usize = 400;
+
+#[derive(serde::Serialize, Debug)]
+pub struct ContributorPromptStats {
+    pub contributor: String,
+    pub emails: Vec<String>,
+    pub commits: usize,
+    pub characters: usize,
+    pub estimated_tokens: usize,
+    /// Whether the prompt would be sent, rather than served from the cache or summarised
+    /// offline.
+    pub sent: bool,
+    /// Names and emails that appear in the prompt as it would be sent.
+    pub identities: Vec<String>,
+}
 
-            for (user_name, commit_data) in squashed_commits {
-                // Check if cancelled
-                if cancellation_state.cancelled.load(Ordering::SeqCst) {
-                    log::info!("Summary generation cancelled by user");
-                    return Err("Summary generation cancelled".to_string());
-                }
+#[derive(serde::Serialize, Debug)]
+pub struct SummaryDryRun {
+    pub provider: String,
+    pub model: Option<String>,
+    pub pseudonymised: bool,
+    pub contributors: Vec<ContributorPromptStats>,
+    /// Totals of the prompts that would be sent.
+    pub total_characters: usize,
+    pub total_estimated_tokens: usize,
+    pub estimated_output_tokens: usize,
+    /// Rough cost in USD at list prices, if the model's pricing is known.
+    pub estimated_cost: Option<f64>,
+    pub identities: Vec<String>,
+}
 
-                if !commit_data.is_empty() {
-                    match summarize_commits(&commit_data).await {
-                        Ok(summary) => {
-                            // Send progress for each email associated with this user
-                            if let Some(emails) = user_to_emails.get(&user_name) {
-                                for email in emails {
-                                    let progress = SummaryProgress {
-                                        email: email.clone(),
-                                        summary: summary.clone(),
-                                    };
-                                    window.emit("summary-progress", progress).unwrap();
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            let err_msg = e.to_string();
-                            Err(err_msg)?
-                        }
-                    }
-                }
-            }
-            Ok(())
-        }
-        Err(e) => {
-            let msg = format!("Failed to get squashed commits for path {path}: {e}");
-            log::error!("{msg}");
-            Err(msg)
-        }
+/// Builds every prompt `get_ai_summary_with_config` would send, without sending anything,
+/// and reports how much data would leave the machine, what it would roughly cost and which
+/// names and emails it contains.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn preview_ai_summary_with_config(
+    path: &str,
+    config_json: Value,
+    branch: Option<&str>,
+    date_range: Option<DateRange>,
+    regex_query: Option<&str>,
+    force_regenerate: Option<Vec<String>>,
+    structured: Option<bool>,
+    pseudonymise: Option<bool>,
+) -> Result<SummaryDryRun, String> {
+    let filter = CommitFilter::new(branch, date_range, regex_query)?;
+    let force_regenerate = force_regenerate.unwrap_or_default();
+    let structured = structured.unwrap_or(false);
+    let pseudonymise = pseudonymise.unwrap_or(false);
+
+    let jobs = get_config_jobs(path, config_json, &filter, &force_regenerate).await?;
+    let all_identities = get_job_identities(path, &jobs)?;
+    let config = llm::get_config();
+    let offline = matches!(config, llm::LlmConfig::Offline);
+    let options = summary_input::get_options();
+    let template = if structured {
+        STRUCTURED_SUMMARY_PROMPT.to_string()
+    } else {
+        prompt_templates::active_template()
+    };
+    let date_range = describe_date_range(filter.date_range.as_ref());
+
+    let mut contributors = Vec::new();
+    for job in jobs {
+        let context = SummaryContext {
+            repo_path: path.to_string(),
+            contributor: job.contributor.clone(),
+            date_range: date_range.clone(),
+            identities: pseudonymise.then(|| all_identities.clone()),
+        };
+        let (prompt, _) = build_prompt(&context, &job.commits, &template, &options)?;
+        let key = summary_cache::cache_key(&config, &prompt, &job.commits);
+        let cached = !job.force && summary_cache::read(&key).await.is_some();
+
+        contributors.push(ContributorPromptStats {
+            contributor: job.contributor,
+            emails: job.emails,
+            commits: job.commits.len(),
+            characters: prompt.chars().count(),
+            estimated_tokens: estimate_tokens(&prompt),
+            sent: !offline && !cached,
+            identities: find_identities(&prompt, &all_identities),
+        });
     }
+    contributors.sort_by(|a, b| a.contributor.cmp(&b.contributor));
+
+    let sent: Vec<&ContributorPromptStats> = contributors.iter().filter(|c| c.sent).collect();
+    let total_estimated_tokens = sent.iter().map(|c| c.estimated_tokens).sum();
+    let output_tokens_each = if structured {
+        STRUCTURED_OUTPUT_TOKENS
+    } else {
+        SUMMARY_OUTPUT_TOKENS
+    };
+    let estimated_output_tokens = sent.len() * output_tokens_each;
+    let identities: BTreeSet<String> = sent
+        .iter()
+        .flat_map(|c| c.identities.iter().cloned())
+        .collect();
+
+    Ok(SummaryDryRun {
+        provider: config.provider_name().to_string(),
+        model: config.models().first().cloned(),
+        pseudonymised: pseudonymise,
+        total_characters: sent.iter().map(|c| c.characters).sum(),
+        total_estimated_tokens,
+        estimated_output_tokens,
+        estimated_cost: llm::estimate_cost(
+            &config,
+            total_estimated_tokens,
+            estimated_output_tokens,
+        ),
+        identities: identities.into_iter().collect(),
+        contributors,
+    })
 }
 
-#[tauri::command]
-pub fn cancel_summary_generation(cancellation_state: State<'_, CancellationState>) {
-    cancellation_state.cancelled.store(true, Ordering::SeqCst);
-    log::info!("Cancellation requested for summary generation");
+/// Cancels the summary job with `job_id`, or every running job if none is given.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cancel_summary_generation(
+    job_id: Option<String>,
+    cancellation_state: State<'_, CancellationState>,
+) {
+    let cancelled = cancellation_state.cancel(job_id.as_deref());
+    log::info!("Cancellation requested for {cancelled} summary jobs");
 }
 
 #[tauri::command]
 pub fn check_key_set() -> bool {
-    dotenvy::dotenv().ok();
-    env::var("GEMINI_API_KEY").is_ok()
+    llm::provider_has_credentials(&llm::get_config())
 }
 
+/// Checks a Gemini API key and, if it is valid, saves it in the credential store (or for
+/// this session if the store is locked).
 #[tauri::command]
 pub async fn gemini_key_validation(api_key: String) -> Result<bool, String> {
     log::info!("Validating Gemini API key...");
-    let url: &str = "https://generativelanguage.googleapis.com/v1/models";
-
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30)) //Set a timeout of 30 seconds
-        .connect_timeout(Duration::from_secs(10)) // Set a connection timeout of 10 seconds
-        .build()
-        .map_err(to_string)?;
-
-    let response = client
-        .get(url)
-        .query(&[("key", &api_key)])
-        .send()
-        .await
-        .map_err(to_string)?;
-
-    match response.status() {
-        reqwest::StatusCode::OK => {
-            log::info!("VALID API KEY");
-            env::set_var("GEMINI_API_KEY", &api_key);
-            Ok(true)
-        }
-        reqwest::StatusCode::UNAUTHORIZED
-        | reqwest::StatusCode::FORBIDDEN
-        | reqwest::StatusCode::BAD_REQUEST => {
-            log::info!("INVALID API KEY");
-
-            if env::var("GEMINI_API_KEY").is_ok() {
-                // Removes the previously inputted valid key in case invalid key is entered.
-                env::remove_var("GEMINI_API_KEY");
-            }
-
-            Ok(false)
-        }
-        status => {
-            let body = response.text().await.unwrap_or_default();
-            log::error!("Unexpected validation status {body}: {status}");
-            Err(format!("Unexpected status: {status}"))
-        }
+    let valid = validate_secret(CredentialKind::LlmApiKey, GEMINI_CREDENTIAL, &api_key).await?;
+
+    if valid {
+        log::info!("VALID API KEY");
+        remember_secret(CredentialKind::LlmApiKey, GEMINI_CREDENTIAL, &api_key)?;
+    } else {
+        log::info!("INVALID API KEY");
+        // Removes the previously inputted valid key in case invalid key is entered.
+        forget_session_secret(CredentialKind::LlmApiKey, GEMINI_CREDENTIAL);
     }
+    Ok(valid)
 }
 
-#[derive(Deserialize)]
-struct GeminiResponse {
-    candidates: Vec<Candidate>,
+/// What a summary is generated for, used to fill in the prompt template.
+pub struct SummaryContext {
+    pub repo_path: String,
+    pub contributor: String,
+    pub date_range: String,
+    /// Set when these names and emails should be replaced with aliases in the prompt.
+    pub identities: Option<Arc<Vec<Identity>>>,
 }
 
-#[derive(Deserialize)]
-struct Candidate {
-    content: Content,
+impl SummaryContext {
+    fn prompt_variables(&self, input: SummaryInput) -> PromptVariables {
+        PromptVariables {
+            commits: input.commits,
+            file_summary: input.file_summary,
+            contributor: self.contributor.clone(),
+            repo: repo_name(&self.repo_path),
+            date_range: self.date_range.clone(),
+            ..Default::default()
+        }
+    }
 }
 
-#[derive(Deserialize)]
-struct Content {
-    parts: Vec<Part>,
+pub async fn summarize_commits(prompt: &str, limiter: &llm::RateLimiter) -> Result<String, String> {
+    let config = llm::get_config();
+    if !llm::provider_has_credentials(&config) {
+        return Err("No API key set for the configured AI provider".to_string());
+    }
+    llm::generate(&config, prompt, limiter).await
 }
 
-#[derive(Deserialize)]
-struct Part {
-    text: String,
+// Opens the repository separately so it isn't held across an await point
+fn build_input(
+    repo_path: &str,
+    oids: &[Oid],
+    options: &SummaryInputOptions,
+) -> Result<SummaryInput, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+    build_summary_input(&repo, oids, options).map_err(to_string)
 }
 
-const COMMIT_SUMMARY_PROMPT: &str = include_str!("AI-summary-prompt.md");
-
-pub async fn summarize_commits(commits: &str) -> Result<String, String> {
-    dotenvy::dotenv().ok();
-    let api_key = env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set");
-
-    let models = [
-        "gemini-2.0-flash",
-        "gemini-2.0-flash-lite",
-        "gemini-2.5-flash-lite",
-    ];
-
-    let prompt = COMMIT_SUMMARY_PROMPT.replace("{commits}", commits);
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30)) //Set a timeout of 30 seconds
-        .connect_timeout(Duration::from_secs(10)) // Set a connection timeout of 10 seconds
-        .build()
-        .map_err(|e| format!("Failed to build client: {e}"))?;
-
-    let mut last_error: Option<String> = None;
-
-    for model in &models {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{model}:generateContent?key={api_key}"
-        );
+/// Renders `template` for the commits, with names and emails replaced by aliases if the
+/// context asks for it. The pseudonymiser is returned to restore them in the response.
+fn build_prompt(
+    context: &SummaryContext,
+    oids: &[Oid],
+    template: &str,
+    options: &SummaryInputOptions,
+) -> Result<(String, Option<Pseudonymiser>), String> {
+    let input = build_input(&context.repo_path, oids, options)?;
+    let prompt = context.prompt_variables(input).render(template);
+
+    let Some(ref identities) = context.identities else {
+        return Ok((prompt, None));
+    };
+    // Emails of people outside the team (e.g. in diffs of an AUTHORS file) are replaced too
+    let mut identities = identities.to_vec();
+    identities.extend(find_emails(&prompt).into_iter().map(|email| Identity {
+        name: None,
+        email: Some(email),
+    }));
+    let pseudonymiser = Pseudonymiser::new(&identities);
+    Ok((pseudonymiser.apply(&prompt), Some(pseudonymiser)))
+}
 
-        let res = client
-            .post(&url)
-            .json(&json!({
-                "contents": [{
-                    "parts": [{"text": &prompt}]
-                }]
-            }))
-            .send()
-            .await;
+/// Summarises the given commits, serving the cached summary when the same commits have been
+/// summarised before with the same provider, models and prompt, unless `force` is set.
+pub async fn summarize_with_cache(
+    context: &SummaryContext,
+    oids: &[Oid],
+    force: bool,
+    structured: bool,
+    limiter: &llm::RateLimiter,
+) -> Result<SummaryResult, String> {
+    let config = llm::get_config();
+    if matches!(config, llm::LlmConfig::Offline) {
+        // Cheap and deterministic, so not worth caching
+        let summary = offline_summary::summarize(&context.repo_path, oids)?;
+        return Ok(SummaryResult {
+            summary: summary.summary.clone(),
+            structured: structured.then_some(summary),
+        });
+    }
 
-        match res {
-            Ok(response) => match response.json::<GeminiResponse>().await {
-                Ok(response_json) => {
-                    if let Some(candidate) = response_json.candidates.first() {
-                        if let Some(part) = candidate.content.parts.first() {
-                            return Ok(part.text.clone());
-                        }
-                    }
-                    last_error = Some(format!("Empty response from model {model}"));
-                    log::error!("{last_error:?}");
-                }
-                Err(e) => {
-                    last_error = Some(format!("Failed to parse response from model {model}: {e}"));
-                    log::error!("{last_error:?}");
-                }
-            },
-            Err(e) => {
-                let error_msg = if e.is_timeout() {
-                    format!("Request to model {model} timed out")
-                } else if e.is_connect() {
-                    "Network connection error. Please check internet connection.".to_string()
-                } else if e.is_request() {
-                    format!("Request error to model {model}. Please try again.")
-                } else {
-                    format!("Request to model {model} failed. Unknown Error: {e}")
-                };
-                log::error!("{error_msg}");
-                last_error = Some(error_msg);
+    let options = summary_input::get_options();
+    let template = if structured {
+        STRUCTURED_SUMMARY_PROMPT.to_string()
+    } else {
+        prompt_templates::active_template()
+    };
+    let (prompt, pseudonymiser) = build_prompt(context, oids, &template, &options)?;
+    let key = summary_cache::cache_key(&config, &prompt, oids);
+
+    if !force {
+        if let Some(cached) = summary_cache::read(&key).await {
+            // Structured summaries are cached as their JSON
+            let result = if structured {
+                serde_json::from_str::<StructuredSummary>(&cached)
+                    .ok()
+                    .map(|s| SummaryResult {
+                        summary: s.summary.clone(),
+                        structured: Some(s),
+                    })
+            } else {
+                Some(SummaryResult {
+                    summary: cached,
+                    structured: None,
+                })
+            };
+            if let Some(result) = result {
+                log::info!("Using cached summary {key}");
+                return Ok(result);
             }
         }
     }
 
-    // Err(String::from(
-    //     "Failed to generate summary. Check internet connection or API key validity.",
-    // ))
+    let result = if structured {
+        let mut summary = summarize_structured(&prompt, oids, limiter).await?;
+        if let Some(ref pseudonymiser) = pseudonymiser {
+            summary = pseudonymiser.restore_structured(summary);
+        }
+        let json = serde_json::to_string(&summary).map_err(to_string)?;
+        summary_cache::write(&key, &json, &config, oids.len(), &context.repo_path).await;
+        SummaryResult {
+            summary: summary.summary.clone(),
+            structured: Some(summary),
+        }
+    } else {
+        let mut summary = summarize_commits(&prompt, limiter).await?;
+        if let Some(ref pseudonymiser) = pseudonymiser {
+            summary = pseudonymiser.restore(&summary);
+        }
+        summary_cache::write(&key, &summary, &config, oids.len(), &context.repo_path).await;
+        SummaryResult {
+            summary,
+            structured: None,
+        }
+    };
+    Ok(result)
+}
 
-    Err(last_error.unwrap_or_else(|| {
-        String::from("Failed to generate summary. Check internet connection or API key validity.")
-    }))
+/// Renders the prompt that would be sent to summarise `contributor_name`'s commits, using
+/// `template` or the active template, without calling the provider.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn preview_summary_prompt(
+    path: &str,
+    contributor_name: &str,
+    template: Option<String>,
+    branch: Option<&str>,
+    date_range: Option<DateRange>,
+    regex_query: Option<&str>,
+) -> Result<String, String> {
+    let template = match template {
+        Some(name) => {
+            let content = prompt_templates::load_template(&name)?;
+            prompt_templates::validate_template(&content)?;
+            content
+        }
+        None => prompt_templates::active_template(),
+    };
+
+    let filter = CommitFilter::new(branch, date_range, regex_query)?;
+    let oids = get_contributor_commits(path, contributor_name, &filter)?;
+    if oids.is_empty() {
+        return Err(format!("No commits found for {contributor_name}"));
+    }
+
+    let input = build_input(path, &oids, &summary_input::get_options())?;
+    let context = SummaryContext {
+        repo_path: path.to_string(),
+        contributor: contributor_name.to_string(),
+        date_range: describe_date_range(filter.date_range.as_ref()),
+        identities: None,
+    };
+    Ok(context.prompt_variables(input).render(&template))
 }
 
+/// Commits by `contributor_name` selected by `filter`, newest first.
 pub fn get_contributor_commits(
     repo_path: &str,
     contributor_name: &str,
-) -> Result<String, git2::Error> {
-    let repo = Repository::open(repo_path)?;
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
+    filter: &CommitFilter,
+) -> Result<Vec<Oid>, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+    let mut oids = Vec::new();
 
-    let mut commits = String::new();
-    let mut count = 0;
-
-    for oid in revwalk {
-        if count >= 10 {
-            break;
-        }
-
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
+    for oid in filter.walk(&repo)? {
+        let commit = repo.find_commit(oid).map_err(to_string)?;
         let author_signature = commit.author();
 
-        if let Some(author) = author_signature.name() {
-            if author == contributor_name {
-                if let Some(message) = commit.summary() {
-                    commits.push_str(message);
-                    commits.push('\n');
-                    count += 1;
-                }
-            }
+        if author_signature.name() == Some(contributor_name) {
+            oids.push(oid);
         }
     }
 
-    Ok(commits)
+    Ok(oids)
 }
 
-pub fn get_all_contributors(repo_path: &str) -> Result<HashSet<(String, String)>, git2::Error> {
-    let repo = Repository::open(repo_path)?;
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-
+pub fn get_all_contributors(
+    repo_path: &str,
+    filter: &CommitFilter,
+) -> Result<HashSet<(String, String)>, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
     let mut contributors = HashSet::new();
 
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
+    for oid in filter.walk(&repo)? {
+        let commit = repo.find_commit(oid).map_err(to_string)?;
         let author_signature = commit.author();
 
         if let (Some(author), Some(email)) = (author_signature.name(), author_signature.email()) {
@@ -367,14 +849,14 @@ pub fn get_all_contributors(repo_path: &str) -> Result<HashSet<(String, String)>
     Ok(contributors)
 }
 
+/// Commits selected by `filter` grouped by the user their author email is mapped to in
+/// `config_json`, newest first. Authors missing from the mapping are left out.
 pub async fn get_squashed_commits_by_config(
     repo_path: &str,
     config_json: Value,
-) -> Result<HashMap<String, String>, git2::Error> {
-    let repo = Repository::open(repo_path)?;
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-    revwalk.set_sorting(Sort::TIME)?;
+    filter: &CommitFilter,
+) -> Result<HashMap<String, Vec<Oid>>, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
 
     let mut email_to_user: HashMap<String, String> = HashMap::new();
 
@@ -390,39 +872,18 @@ pub async fn get_squashed_commits_by_config(
         }
     }
 
-    let mut user_commits: HashMap<String, Vec<(i64, String)>> = HashMap::new();
+    let mut user_commits: HashMap<String, Vec<Oid>> = HashMap::new();
 
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
+    for oid in filter.walk(&repo)? {
+        let commit = repo.find_commit(oid).map_err(to_string)?;
         let author_signature = commit.author();
 
         if let Some(email) = author_signature.email() {
             if let Some(user_name) = email_to_user.get(email) {
-                if let Some(message) = commit.summary() {
-                    let commit_time = commit.time().seconds();
-                    user_commits
-                        .entry(user_name.clone())
-                        .or_default()
-                        .push((commit_time, message.to_string()));
-                }
+                user_commits.entry(user_name.clone()).or_default().push(oid);
             }
         }
     }
 
-    let mut result: HashMap<String, String> = HashMap::new();
-
-    for (user_name, mut commits) in user_commits {
-        commits.sort_by(|a, b| a.0.cmp(&b.0));
-
-        let squashed_commits = commits
-            .iter()
-            .map(|(_, message)| message.clone())
-            .collect::<Vec<String>>()
-            .join("\n");
-
-        result.insert(user_name, squashed_commits);
-    }
-
-    Ok(result)
+    Ok(user_commits)
 }
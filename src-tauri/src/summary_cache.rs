@@ -0,0 +1,154 @@
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::llm::LlmConfig;
+use crate::manifest::get_cache_directory;
+use crate::settings::moved_path;
+use crate::utils::to_string;
+
+#[derive(Serialize, Deserialize)]
+struct CachedSummary {
+    summary: String,
+    config: LlmConfig,
+    commit_count: usize,
+    created_at: String,
+    /// The repository the summary is for, so bundles can export one workspace's summaries.
+    #[serde(default)]
+    repo_path: Option<String>,
+}
+
+async fn get_summary_cache_directory() -> PathBuf {
+    let mut path = get_cache_directory().await;
+    path.push("summaries");
+    path
+}
+
+/// Identifies a summary by everything that determines it: the provider and models, the
+/// rendered prompt and the exact set of commits. The rendered prompt covers the template, its
+/// variables (contributor, repository, date range), how the commit input was built and any
+/// pseudonymisation.
+pub fn cache_key(config: &LlmConfig, prompt: &str, oids: &[Oid]) -> String {
+    let mut oids: Vec<String> = oids.iter().map(|oid| oid.to_string()).collect();
+    oids.sort();
+
+    let mut context = md5::Context::new();
+    context.consume(serde_json::to_string(config).unwrap_or_default());
+    context.consume(prompt);
+    for oid in &oids {
+        context.consume(oid);
+    }
+    format!("{:x}", context.finalize())
+}
+
+pub async fn read(key: &str) -> Option<String> {
+    let path = get_summary_cache_directory()
+        .await
+        .join(format!("{key}.json"));
+    let content = std::fs::read_to_string(path).ok()?;
+    let cached: CachedSummary = serde_json::from_str(&content).ok()?;
+    Some(cached.summary)
+}
+
+pub async fn write(
+    key: &str,
+    summary: &str,
+    config: &LlmConfig,
+    commit_count: usize,
+    repo_path: &str,
+) {
+    let dir = get_summary_cache_directory().await;
+    let cached = CachedSummary {
+        summary: summary.to_string(),
+        config: config.clone(),
+        commit_count,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        repo_path: Some(repo_path.to_string()),
+    };
+
+    // A failed write only means the summary is regenerated next time
+    let result = std::fs::create_dir_all(&dir)
+        .map_err(to_string)
+        .and_then(|_| serde_json::to_string_pretty(&cached).map_err(to_string))
+        .and_then(|content| {
+            std::fs::write(dir.join(format!("{key}.json")), content).map_err(to_string)
+        });
+    if let Err(e) = result {
+        log::warn!("Failed to cache summary {key}: {e}");
+    }
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    a.trim_end_matches(['/', '\\']) == b.trim_end_matches(['/', '\\'])
+}
+
+/// Cache files of the summaries of any of `repo_paths`. Entries written before summaries
+/// recorded their repository are never included.
+pub async fn entries_for_repositories(repo_paths: &[&str]) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(get_summary_cache_directory().await) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            std::fs::read_to_string(path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<CachedSummary>(&content).ok())
+                .and_then(|cached| cached.repo_path)
+                .is_some_and(|repo| repo_paths.iter().any(|p| same_path(p, &repo)))
+        })
+        .collect()
+}
+
+/// Points a cache entry from another machine at the repository's path on this one.
+pub fn remap_repo_path(content: &str, new_paths: &[(String, String)]) -> Option<String> {
+    let mut cached: CachedSummary = serde_json::from_str(content).ok()?;
+    let old = cached.repo_path.as_deref()?;
+    let (_, new) = new_paths.iter().find(|(from, _)| same_path(from, old))?;
+    cached.repo_path = Some(new.clone());
+    serde_json::to_string_pretty(&cached).ok()
+}
+
+/// Points the cached summaries of repositories in the data directory at their new paths after
+/// it moved from `old_dir` to `new_dir`. Entries that can't be rewritten are left as they were.
+pub async fn move_repo_paths(old_dir: &Path, new_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(get_summary_cache_directory().await) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let Some(mut cached) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CachedSummary>(&content).ok())
+        else {
+            continue;
+        };
+        let Some(new_path) = cached
+            .repo_path
+            .as_deref()
+            .and_then(|repo| moved_path(repo, old_dir, new_dir))
+        else {
+            continue;
+        };
+        cached.repo_path = Some(new_path);
+        let result = serde_json::to_string_pretty(&cached)
+            .map_err(to_string)
+            .and_then(|content| std::fs::write(&path, content).map_err(to_string));
+        if let Err(e) = result {
+            log::warn!("Failed to update the repository path of {path:?}: {e}");
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn clear_summary_cache() -> Result<(), String> {
+    let dir = get_summary_cache_directory().await;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| {
+            log::error!("Failed to clear summary cache: {e}");
+            format!("Failed to clear summary cache: {e}")
+        })?;
+    }
+    log::info!("Cleared summary cache");
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use git2::{DiffOptions, Oid, Patch, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::settings::{read_settings, write_settings};
+use crate::utils::format_date;
+
+// Files whose diffs say little about the work done and would crowd out real changes
+const GENERATED_FILES: [&str; 6] = [
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "poetry.lock",
+    "go.sum",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SummaryInputOptions {
+    /// Approximate number of tokens the commit input may take up in the prompt.
+    pub token_budget: usize,
+    pub max_files_per_commit: usize,
+    pub max_hunks_per_file: usize,
+    pub max_lines_per_hunk: usize,
+}
+
+impl Default for SummaryInputOptions {
+    fn default() -> Self {
+        SummaryInputOptions {
+            token_budget: 3000,
+            max_files_per_commit: 8,
+            max_hunks_per_file: 2,
+            max_lines_per_hunk: 12,
+        }
+    }
+}
+
+/// The commit section of a summary prompt.
+pub struct SummaryInput {
+    /// Overview of the areas and file types touched.
+    pub file_summary: String,
+    /// The overview followed by the commit history and diff excerpts.
+    pub commits: String,
+}
+
+#[derive(Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub additions: usize,
+    pub deletions: usize,
+    pub excerpt: String,
+}
+
+#[derive(Clone)]
+pub struct CommitChanges {
+    pub oid: Oid,
+    pub time: i64,
+    pub message: String,
+    pub files: Vec<FileChange>,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+impl CommitChanges {
+    /// Lines changed outside of generated files.
+    pub fn size(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|f| !is_generated(&f.path))
+            .map(|f| f.additions + f.deletions)
+            .sum()
+    }
+
+    fn short_id(&self) -> String {
+        self.oid.to_string().chars().take(7).collect()
+    }
+
+    fn main_area(&self) -> String {
+        self.files
+            .iter()
+            .filter(|f| !is_generated(&f.path))
+            .max_by_key(|f| f.additions + f.deletions)
+            .map(|f| area_of(&f.path))
+            .unwrap_or_default()
+    }
+}
+
+/// Rough token estimate (about four characters per token for English text and code).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn is_generated(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    GENERATED_FILES.contains(&file_name) || file_name.ends_with(".min.js")
+}
+
+/// The directory two levels deep, e.g. "src/lib/components/Foo.svelte" -> "src/lib".
+pub fn area_of(path: &str) -> String {
+    let parts: Vec<&str> = path.split('/').collect();
+    match parts.len() {
+        0 | 1 => String::from("(root)"),
+        2 => parts[0].to_string(),
+        _ => format!("{}/{}", parts[0], parts[1]),
+    }
+}
+
+/// Files changed by the commit, largest first, with diff excerpts limited by `options`.
+pub fn collect_changes(
+    repo: &Repository,
+    oid: Oid,
+    options: &SummaryInputOptions,
+) -> Result<CommitChanges, git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let commit_tree = commit.tree()?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.context_lines(0);
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&commit_tree),
+        Some(&mut diff_opts),
+    )?;
+
+    let mut files = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, idx)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let binary = delta.flags().is_binary();
+        let (_, additions, deletions) = patch.line_stats()?;
+
+        let mut excerpt = String::new();
+        if !binary && !is_generated(&path) {
+            for hunk_idx in 0..patch.num_hunks().min(options.max_hunks_per_file) {
+                let (hunk, line_count) = patch.hunk(hunk_idx)?;
+                excerpt.push_str(String::from_utf8_lossy(hunk.header()).trim_end());
+                excerpt.push('\n');
+                for line_idx in 0..line_count.min(options.max_lines_per_hunk) {
+                    let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                    excerpt.push(line.origin());
+                    excerpt.push_str(String::from_utf8_lossy(line.content()).trim_end());
+                    excerpt.push('\n');
+                }
+                if line_count > options.max_lines_per_hunk {
+                    excerpt.push_str("...\n");
+                }
+            }
+        }
+
+        files.push(FileChange {
+            path,
+            additions,
+            deletions,
+            excerpt,
+        });
+    }
+
+    // Biggest file changes first so truncation keeps the most informative parts
+    files.sort_by_key(|f| std::cmp::Reverse(f.additions + f.deletions));
+
+    Ok(CommitChanges {
+        oid,
+        time: commit.time().seconds(),
+        message: commit.summary().unwrap_or("").to_string(),
+        additions: files.iter().map(|f| f.additions).sum(),
+        deletions: files.iter().map(|f| f.deletions).sum(),
+        files,
+    })
+}
+
+fn overview(commits: &[CommitChanges]) -> String {
+    let additions: usize = commits.iter().map(|c| c.additions).sum();
+    let deletions: usize = commits.iter().map(|c| c.deletions).sum();
+    let first = commits
+        .first()
+        .map(|c| format_date(c.time))
+        .unwrap_or_default();
+    let last = commits
+        .last()
+        .map(|c| format_date(c.time))
+        .unwrap_or_default();
+
+    let mut area_lines: HashMap<String, usize> = HashMap::new();
+    let mut extension_lines: HashMap<String, usize> = HashMap::new();
+    for file in commits.iter().flat_map(|c| c.files.iter()) {
+        if is_generated(&file.path) {
+            continue;
+        }
+        let lines = file.additions + file.deletions;
+        *area_lines.entry(area_of(&file.path)).or_default() += lines;
+        if let Some((_, ext)) = file.path.rsplit_once('.') {
+            *extension_lines.entry(ext.to_string()).or_default() += lines;
+        }
+    }
+
+    let top = |counts: HashMap<String, usize>| {
+        let total: usize = counts.values().sum::<usize>().max(1);
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+            .iter()
+            .take(5)
+            .map(|(name, lines)| format!("{name} ({}%)", lines * 100 / total))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    format!(
+        "Overview: {} commits between {first} and {last}, +{additions}/-{deletions} lines.\nMost changed areas: {}\nFile types: {}\n",
+        commits.len(),
+        top(area_lines),
+        top(extension_lines),
+    )
+}
+
+fn history_line(commit: &CommitChanges) -> String {
+    format!(
+        "- {} {} \"{}\" ({} files, +{}/-{})\n",
+        format_date(commit.time),
+        commit.short_id(),
+        commit.message,
+        commit.files.len(),
+        commit.additions,
+        commit.deletions,
+    )
+}
+
+fn detail_block(commit: &CommitChanges, options: &SummaryInputOptions) -> String {
+    let mut block = format!("### {} {}\n", commit.short_id(), commit.message);
+    for file in commit.files.iter().take(options.max_files_per_commit) {
+        block.push_str(&format!(
+            "{} (+{}/-{})\n",
+            file.path, file.additions, file.deletions
+        ));
+        block.push_str(&file.excerpt);
+    }
+    if commit.files.len() > options.max_files_per_commit {
+        block.push_str(&format!(
+            "({} more files)\n",
+            commit.files.len() - options.max_files_per_commit
+        ));
+    }
+    block
+}
+
+// Largest commit of each area first, then the next largest of each area, and so on, so the
+// detailed section covers the breadth of the work rather than one big area.
+fn detail_order(commits: &[CommitChanges]) -> Vec<usize> {
+    let mut by_area: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, commit) in commits.iter().enumerate() {
+        by_area.entry(commit.main_area()).or_default().push(idx);
+    }
+    let mut areas: Vec<Vec<usize>> = by_area
+        .into_values()
+        .map(|mut idxs| {
+            idxs.sort_by_key(|&i| std::cmp::Reverse(commits[i].size()));
+            idxs
+        })
+        .collect();
+    areas.sort_by_key(|idxs| std::cmp::Reverse(commits[idxs[0]].size()));
+
+    let mut order = Vec::new();
+    let mut round = 0;
+    while order.len() < commits.len() {
+        for idxs in &areas {
+            if let Some(&idx) = idxs.get(round) {
+                order.push(idx);
+            }
+        }
+        round += 1;
+    }
+    order
+}
+
+// Evenly spaced subset of the history when listing every commit would not fit
+fn sample_history(commits: &[CommitChanges], budget: usize) -> String {
+    let lines: Vec<String> = commits.iter().map(history_line).collect();
+    let total: usize = lines.iter().map(|l| estimate_tokens(l)).sum();
+    if total <= budget {
+        return lines.concat();
+    }
+
+    let average = (total / lines.len()).max(1);
+    let keep = (budget / average).clamp(1, lines.len());
+    let mut sampled = String::new();
+    let mut kept = HashSet::new();
+    for i in 0..keep {
+        let idx = i * lines.len() / keep;
+        if kept.insert(idx) {
+            sampled.push_str(&lines[idx]);
+        }
+    }
+    sampled.push_str(&format!(
+        "({} more commits not listed)\n",
+        lines.len() - kept.len()
+    ));
+    sampled
+}
+
+/// Builds the commit section of a summary prompt from the given commits: an overview of the
+/// areas and file types touched, the full commit history (sampled if needed) and diff
+/// excerpts of the largest changes, staying within `options.token_budget`.
+pub fn build_summary_input(
+    repo: &Repository,
+    oids: &[Oid],
+    options: &SummaryInputOptions,
+) -> Result<SummaryInput, git2::Error> {
+    let commits = oids
+        .iter()
+        .map(|oid| collect_changes(repo, *oid, options))
+        .collect::<Result<Vec<CommitChanges>, git2::Error>>()?;
+    Ok(summary_input_from_changes(commits, options))
+}
+
+/// Like `build_summary_input`, for changes already collected with `collect_changes`.
+pub fn summary_input_from_changes(
+    mut commits: Vec<CommitChanges>,
+    options: &SummaryInputOptions,
+) -> SummaryInput {
+    if commits.is_empty() {
+        return SummaryInput {
+            file_summary: String::new(),
+            commits: String::new(),
+        };
+    }
+    commits.sort_by_key(|c| c.time);
+
+    let file_summary = overview(&commits);
+    let mut input = file_summary.clone();
+    let remaining = options.token_budget.saturating_sub(estimate_tokens(&input));
+
+    // Keep at least half of the remaining budget for diff excerpts
+    input.push_str("\nCommit history (oldest first):\n");
+    input.push_str(&sample_history(&commits, remaining / 2));
+
+    let mut remaining = options.token_budget.saturating_sub(estimate_tokens(&input));
+    let mut details = String::new();
+    for idx in detail_order(&commits) {
+        if commits[idx].size() == 0 {
+            continue;
+        }
+        let block = detail_block(&commits[idx], options);
+        let cost = estimate_tokens(&block);
+        if cost > remaining {
+            continue;
+        }
+        remaining -= cost;
+        details.push_str(&block);
+    }
+
+    if !details.is_empty() {
+        input.push_str("\nLargest changes:\n");
+        input.push_str(&details);
+    }
+    SummaryInput {
+        file_summary,
+        commits: input,
+    }
+}
+
+pub fn get_options() -> SummaryInputOptions {
+    read_settings().summary_input
+}
+
+#[tauri::command]
+pub fn get_summary_input_options() -> SummaryInputOptions {
+    get_options()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn set_summary_input_options(options: SummaryInputOptions) -> Result<(), String> {
+    if options.token_budget < 100 {
+        return Err("Token budget must be at least 100 tokens".to_string());
+    }
+    let mut settings = read_settings();
+    settings.summary_input = options;
+    write_settings(&settings)
+}
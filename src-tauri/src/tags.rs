@@ -0,0 +1,69 @@
+use git2::Repository;
+use serde::Serialize;
+
+use crate::utils::to_string;
+
+#[derive(Serialize, Debug)]
+pub struct TagInfo {
+    pub name: String,
+    pub annotated: bool,
+    /// The commit the tag points to.
+    pub commit: String,
+    pub commit_time: i64,
+    /// When an annotated tag was created, otherwise the commit time.
+    pub date: i64,
+    /// Who created an annotated tag.
+    pub tagger: Option<String>,
+    /// The annotated tag's message, or the tagged commit's summary for lightweight tags.
+    pub message: String,
+}
+
+/// Tags that point at commits, oldest commit first.
+pub fn read_tags(repo: &Repository) -> Result<Vec<TagInfo>, String> {
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None).map_err(to_string)?.iter().flatten() {
+        let reference = repo
+            .find_reference(&format!("refs/tags/{name}"))
+            .map_err(to_string)?;
+        // Tags can also point at trees or blobs, which have no place in the history
+        let Ok(commit) = reference.peel_to_commit() else {
+            log::warn!("Skipping tag {name}, which doesn't point at a commit");
+            continue;
+        };
+        let annotation = reference.target().and_then(|oid| repo.find_tag(oid).ok());
+
+        let commit_time = commit.time().seconds();
+        tags.push(match annotation {
+            Some(tag) => TagInfo {
+                name: name.to_string(),
+                annotated: true,
+                commit: commit.id().to_string(),
+                commit_time,
+                date: tag.tagger().map_or(commit_time, |t| t.when().seconds()),
+                tagger: tag.tagger().and_then(|t| t.name().map(String::from)),
+                message: tag.message().unwrap_or_default().trim().to_string(),
+            },
+            None => TagInfo {
+                name: name.to_string(),
+                annotated: false,
+                commit: commit.id().to_string(),
+                commit_time,
+                date: commit_time,
+                tagger: None,
+                message: commit.summary().unwrap_or_default().to_string(),
+            },
+        });
+    }
+
+    tags.sort_by(|a, b| a.commit_time.cmp(&b.commit_time).then(a.name.cmp(&b.name)));
+    Ok(tags)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_tags(path: &str) -> Result<Vec<TagInfo>, String> {
+    let canonical_path = std::path::Path::new(path)
+        .canonicalize()
+        .map_err(to_string)?;
+    let repo = Repository::open(canonical_path).map_err(to_string)?;
+    read_tags(&repo)
+}
@@ -0,0 +1,260 @@
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::{Emitter, State};
+
+use crate::contributor::DateRange;
+use crate::llm;
+use crate::prompt_templates::PromptVariables;
+use crate::structured_summary::{extract_json, generate_validated};
+use crate::summary::{
+    get_squashed_commits_by_config, start_job, until_cancelled, CancellationState, CommitFilter,
+};
+use crate::summary_cache;
+use crate::summary_input::{
+    self, collect_changes, summary_input_from_changes, CommitChanges, SummaryInputOptions,
+};
+use crate::utils::{format_date, to_string};
+
+const TEAM_SUMMARY_PROMPT: &str = include_str!("AI-team-summary-prompt.md");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Milestone {
+    pub date: String,
+    pub title: String,
+    pub description: String,
+    pub contributors: Vec<String>,
+}
+
+// The part of the team summary written by the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct TeamNarrative {
+    narrative: String,
+    milestones: Vec<Milestone>,
+    balance_note: String,
+}
+
+/// Numbers behind the balance note, computed from the repository rather than by the model.
+#[derive(Serialize, Debug, Clone)]
+pub struct TeamMemberStats {
+    pub name: String,
+    pub commits: usize,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Percentage of the team's commits.
+    pub commit_share: f64,
+    /// Percentage of the team's changed lines.
+    pub line_share: f64,
+    pub first_commit: i64,
+    pub last_commit: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TeamSummary {
+    pub job_id: String,
+    pub narrative: String,
+    pub milestones: Vec<Milestone>,
+    pub balance_note: String,
+    pub members: Vec<TeamMemberStats>,
+}
+
+fn member_stats(name: &str, changes: &[&CommitChanges]) -> TeamMemberStats {
+    let mut stats = TeamMemberStats {
+        name: name.to_string(),
+        commits: changes.len(),
+        additions: 0,
+        deletions: 0,
+        commit_share: 0.0,
+        line_share: 0.0,
+        first_commit: i64::MAX,
+        last_commit: i64::MIN,
+    };
+
+    for commit in changes {
+        stats.additions += commit.additions;
+        stats.deletions += commit.deletions;
+        stats.first_commit = stats.first_commit.min(commit.time);
+        stats.last_commit = stats.last_commit.max(commit.time);
+    }
+    stats
+}
+
+// Opens the repository separately so it isn't held across an await point. Returns the member
+// stats, the member section of the prompt and the team's commit input. Each commit is diffed
+// once, for both the stats and the prompt.
+fn build_team_input(
+    repo_path: &str,
+    members: &[(String, Vec<Oid>)],
+    options: &SummaryInputOptions,
+) -> Result<(Vec<TeamMemberStats>, String, String), String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+
+    // Ordered by oid so commits made at the same time keep their order, and the prompt its
+    // cache key
+    let mut changes: BTreeMap<Oid, CommitChanges> = BTreeMap::new();
+    for oid in members.iter().flat_map(|(_, oids)| oids) {
+        if !changes.contains_key(oid) {
+            let commit = collect_changes(&repo, *oid, options).map_err(to_string)?;
+            changes.insert(*oid, commit);
+        }
+    }
+    let member_changes: Vec<Vec<&CommitChanges>> = members
+        .iter()
+        .map(|(_, oids)| oids.iter().map(|oid| &changes[oid]).collect())
+        .collect();
+
+    let mut stats: Vec<TeamMemberStats> = members
+        .iter()
+        .zip(&member_changes)
+        .map(|((name, _), commits)| member_stats(name, commits))
+        .collect();
+    let total_commits: usize = stats.iter().map(|s| s.commits).sum::<usize>().max(1);
+    let total_lines: usize = stats
+        .iter()
+        .map(|s| s.additions + s.deletions)
+        .sum::<usize>()
+        .max(1);
+    for member in stats.iter_mut() {
+        member.commit_share = member.commits as f64 * 100.0 / total_commits as f64;
+        member.line_share =
+            (member.additions + member.deletions) as f64 * 100.0 / total_lines as f64;
+    }
+
+    let mut member_section = String::new();
+    for (member, commits) in stats.iter().zip(&member_changes) {
+        member_section.push_str(&format!(
+            "- {}: {} commits, +{}/-{} lines ({:.0}% of commits, {:.0}% of lines), {} to {}\n",
+            member.name,
+            member.commits,
+            member.additions,
+            member.deletions,
+            member.commit_share,
+            member.line_share,
+            format_date(member.first_commit),
+            format_date(member.last_commit),
+        ));
+        let commits = commits.iter().map(|&commit| commit.clone()).collect();
+        let input = summary_input_from_changes(commits, options);
+        for line in input.file_summary.lines().skip(1) {
+            member_section.push_str(&format!("  {line}\n"));
+        }
+    }
+
+    let commits = summary_input_from_changes(changes.into_values().collect(), options).commits;
+
+    Ok((stats, member_section, commits))
+}
+
+fn parse_and_validate(text: &str) -> Result<TeamNarrative, String> {
+    let parsed: TeamNarrative =
+        serde_json::from_str(extract_json(text)).map_err(|e| format!("Invalid JSON: {e}"))?;
+
+    if parsed.narrative.trim().is_empty() {
+        return Err("\"narrative\" must not be empty".to_string());
+    }
+    if !(2..=8).contains(&parsed.milestones.len()) {
+        return Err("\"milestones\" must have between 2 and 8 entries".to_string());
+    }
+    if parsed.balance_note.trim().is_empty() {
+        return Err("\"balance_note\" must not be empty".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Summarises the whole team mapped in `config_json`: what the project is, its major
+/// milestones and how evenly the work was divided. The result is emitted as `team-summary`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_team_summary(
+    window: tauri::Window,
+    path: &str,
+    config_json: Value,
+    branch: Option<&str>,
+    date_range: Option<DateRange>,
+    regex_query: Option<&str>,
+    force_regenerate: Option<bool>,
+    job_id: Option<String>,
+    cancellation_state: State<'_, CancellationState>,
+) -> Result<(), String> {
+    let job = start_job(&window, &cancellation_state, job_id)?;
+
+    let config = llm::get_config();
+    // The offline summariser only describes one contributor's commits
+    if matches!(config, llm::LlmConfig::Offline) {
+        return Err("Team summaries need an AI provider, not the offline summariser".to_string());
+    }
+    if !llm::provider_has_credentials(&config) {
+        return Err("No API key set for the configured AI provider".to_string());
+    }
+
+    let filter = CommitFilter::new(branch, date_range, regex_query)?;
+    let squashed_commits = get_squashed_commits_by_config(path, config_json, &filter).await?;
+
+    let mut members: Vec<(String, Vec<Oid>)> = squashed_commits
+        .into_iter()
+        .filter(|(_, oids)| !oids.is_empty())
+        .collect();
+    if members.is_empty() {
+        let msg = format!("No contributors found in config for repository at path: {path}");
+        log::error!("{msg}");
+        return Err(msg);
+    }
+    members.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+
+    let options = summary_input::get_options();
+    let (stats, member_section, commits) = build_team_input(path, &members, &options)?;
+
+    // The member section covers the email mapping, which the commits alone don't
+    let all_oids: Vec<Oid> = members.iter().flat_map(|(_, oids)| oids.clone()).collect();
+    let prompt = PromptVariables {
+        members: member_section,
+        commits,
+        ..Default::default()
+    }
+    .render(TEAM_SUMMARY_PROMPT);
+    let key = summary_cache::cache_key(&config, &prompt, &all_oids);
+
+    let cached = if force_regenerate.unwrap_or(false) {
+        None
+    } else {
+        summary_cache::read(&key)
+            .await
+            .and_then(|cached| serde_json::from_str::<TeamNarrative>(&cached).ok())
+    };
+
+    let narrative = match cached {
+        Some(narrative) => {
+            log::info!("Using cached team summary {key}");
+            narrative
+        }
+        None => {
+            let generation = llm::get_generation_options();
+            let limiter =
+                llm::RateLimiter::new(generation.requests_per_minute, generation.max_concurrent);
+            let narrative = until_cancelled(
+                &job.token,
+                generate_validated(&prompt, &limiter, "team summary", parse_and_validate),
+            )
+            .await?;
+
+            let json = serde_json::to_string(&narrative).map_err(to_string)?;
+            summary_cache::write(&key, &json, &config, all_oids.len(), path).await;
+            narrative
+        }
+    };
+
+    let summary = TeamSummary {
+        job_id: job.id.clone(),
+        narrative: narrative.narrative,
+        milestones: narrative.milestones,
+        balance_note: narrative.balance_note,
+        members: stats,
+    };
+    window.emit("team-summary", summary).map_err(|e| {
+        log::error!("Failed to emit team summary: {e}");
+        to_string(e)
+    })
+}
@@ -1,190 +1,308 @@
-use regex::Regex;
-use serde::Serialize;
+use git2::Repository;
+use serde::{Deserialize, Serialize};
 use std::path::Path; // Added for local path manipulation
 
+use crate::git_hosts::{self, HostKind, LinkTarget};
+use crate::manifest::get_working_directory;
+use crate::utils::to_string;
+
+// Extensions accepted for archive sources, which `import_archive` can extract
+const ARCHIVE_EXTENSIONS: [&str; 1] = [".zip"];
+
+/// Where a repository comes from, serialised as e.g. `{ "kind": "github" }`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SourceKind {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Gitea,
+    #[serde(rename = "azure_devops")]
+    AzureDevOps,
+    /// A repository already on disk.
+    Local,
+    /// A .zip export of a repository, including its .git directory.
+    Archive,
+}
+
+/// Commands also accept the integer codes (0 GitHub, 1 GitLab, 2 local) that older manifests
+/// use.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SourceKindArg {
+    Kind(SourceKind),
+    Code(i32),
+}
+
+impl TryFrom<SourceKindArg> for SourceKind {
+    type Error = String;
+
+    fn try_from(arg: SourceKindArg) -> Result<Self, Self::Error> {
+        match arg {
+            SourceKindArg::Kind(kind) => Ok(kind),
+            SourceKindArg::Code(0) => Ok(SourceKind::GitHub),
+            SourceKindArg::Code(1) => Ok(SourceKind::GitLab),
+            SourceKindArg::Code(2) => Ok(SourceKind::Local),
+            SourceKindArg::Code(code) => Err(format!(
+                "Invalid source type {code}. Use 0 for GitHub, 1 for GitLab, 2 for Local File."
+            )),
+        }
+    }
+}
+
+impl From<HostKind> for SourceKind {
+    fn from(kind: HostKind) -> Self {
+        match kind {
+            HostKind::GitHub => SourceKind::GitHub,
+            HostKind::GitLab => SourceKind::GitLab,
+            HostKind::Bitbucket => SourceKind::Bitbucket,
+            HostKind::Gitea => SourceKind::Gitea,
+            HostKind::AzureDevOps => SourceKind::AzureDevOps,
+        }
+    }
+}
+
+impl SourceKind {
+    // GitHub and GitLab keep the integer codes so existing clones are still found
+    fn clone_directory_prefix(self) -> &'static str {
+        match self {
+            SourceKind::GitHub => "0",
+            SourceKind::GitLab => "1",
+            SourceKind::Bitbucket => "bitbucket",
+            SourceKind::Gitea => "gitea",
+            SourceKind::AzureDevOps => "azure_devops",
+            SourceKind::Local => "local",
+            SourceKind::Archive => "archive",
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub struct SourceInfo {
     // Renamed from GithubInfo
     pub owner: String, // For git hosts: owner/group. For local: parent directory.
     pub repo: String,  // For git hosts: repo name. For local: file/directory name.
-    pub source_type: i32, // 0 for GitHub, 1 for GitLab, 2 for Local File
+    pub source_type: SourceKind,
+    pub host: Option<String>,         // None for local
+    pub clone_url: Option<String>,    // Canonical HTTPS clone URL. None for local
+    pub target: Option<LinkTarget>,   // Branch, commit or merge request a deep link points at
+    pub local: Option<LocalRepoInfo>, // Only for local repositories
+}
+
+#[derive(Serialize, Debug)]
+pub struct LocalRepoInfo {
+    /// The repository's working directory, or the git directory of a bare repository.
+    pub root: String,
+    /// Where the given path is inside the repository, if it wasn't the root.
+    pub subdirectory: Option<String>,
+    pub bare: bool,
+    pub worktree: bool,
+    pub origin_url: Option<String>,
+    /// None when HEAD is detached.
+    pub head_branch: Option<String>,
+    /// Commits reachable from HEAD; 0 for a repository with no commits yet.
+    pub commit_count: usize,
+}
+
+// Parses a remote URL with the configured git hosts, checking it is of the expected kind
+fn remote_source_info(url_str: &str, source_type: SourceKind) -> Result<SourceInfo, String> {
+    let remote = git_hosts::parse_remote_url(url_str, &git_hosts::get_hosts())?;
+    let kind = SourceKind::from(remote.kind);
+    if kind != source_type {
+        return Err(format!(
+            "URL is from a {kind:?} host, not {source_type:?} ({})",
+            remote.host
+        ));
+    }
+
+    Ok(SourceInfo {
+        owner: remote.owner,
+        repo: remote.repo,
+        source_type,
+        host: Some(remote.host),
+        clone_url: Some(remote.clone_url),
+        target: remote.target,
+        local: None,
+    })
+}
+
+// Owner and repo of an archive, e.g. ("/home/me/Downloads", "project") for
+// /home/me/Downloads/project.zip
+fn archive_source_info(path: &str) -> Result<SourceInfo, String> {
+    let path_obj = Path::new(path);
+    let file_name = path_obj
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let repo = ARCHIVE_EXTENSIONS
+        .iter()
+        .find_map(|ext| {
+            let lower = file_name.to_lowercase();
+            lower
+                .ends_with(ext)
+                .then(|| file_name[..file_name.len() - ext.len()].to_string())
+        })
+        .filter(|repo| !repo.is_empty())
+        .ok_or_else(|| format!("'{path}' is not a .zip archive."))?;
+
+    Ok(SourceInfo {
+        owner: path_obj
+            .parent()
+            .and_then(|p| p.to_str())
+            .unwrap_or(".")
+            .to_string(),
+        repo,
+        source_type: SourceKind::Archive,
+        host: None,
+        clone_url: None,
+        target: None,
+        local: None,
+    })
+}
+
+fn head_branch(repo: &Repository) -> Option<String> {
+    match repo.head() {
+        Ok(head) if head.is_branch() => head.shorthand().map(String::from),
+        Ok(_) => None,
+        // A repository without commits still has HEAD pointing at its first branch
+        Err(_) => repo
+            .find_reference("HEAD")
+            .ok()
+            .and_then(|head| head.symbolic_target().map(String::from))
+            .map(|target| target.trim_start_matches("refs/heads/").to_string()),
+    }
+}
+
+fn commit_count(repo: &Repository) -> Result<usize, String> {
+    if repo.head().is_err() {
+        return Ok(0);
+    }
+    let mut revwalk = repo.revwalk().map_err(to_string)?;
+    revwalk.push_head().map_err(to_string)?;
+    Ok(revwalk.count())
+}
+
+// Opens the repository containing `path`, which may be a subdirectory of it, a worktree or a
+// bare repository. Owner and repo come from the repository root rather than `path`.
+fn local_source_info(path: &str) -> Result<SourceInfo, String> {
+    let path_obj = Path::new(path);
+    if !path_obj.exists() {
+        return Err(format!("'{path}' does not exist."));
+    }
+    let repo = Repository::discover(path_obj).map_err(|e| {
+        log::info!("No repository found at {path}: {e}");
+        format!("'{path}' is not inside a git repository.")
+    })?;
+
+    let bare = repo.is_bare();
+    let root = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => repo.path().to_path_buf(),
+    };
+    let canonical_root = root.canonicalize().map_err(to_string)?;
+    let subdirectory = path_obj
+        .canonicalize()
+        .ok()
+        .and_then(|p| {
+            p.strip_prefix(&canonical_root)
+                .ok()
+                .map(|sub| sub.to_string_lossy().to_string())
+        })
+        .filter(|sub| !sub.is_empty());
+
+    let repo_name = canonical_root
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.trim_end_matches(".git").to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| format!("Could not determine the repository name for '{path}'."))?;
+    let owner = canonical_root
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let origin_url = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|remote| remote.url().map(String::from));
+    let local = LocalRepoInfo {
+        root: canonical_root.to_string_lossy().to_string(),
+        subdirectory,
+        bare,
+        worktree: repo.is_worktree(),
+        origin_url,
+        head_branch: head_branch(&repo),
+        commit_count: commit_count(&repo)?,
+    };
+
+    Ok(SourceInfo {
+        owner,
+        repo: repo_name,
+        source_type: SourceKind::Local,
+        host: None,
+        clone_url: None,
+        target: None,
+        local: Some(local),
+    })
 }
 
+/// The kind of source `url_str` is: archives by their extension, URLs by their git host and
+/// anything else as a local path. Nothing is read from disk or the network.
+#[tauri::command(rename_all = "snake_case")]
+pub fn detect_source_kind(url_str: &str) -> Result<SourceKind, String> {
+    let lower = url_str.to_lowercase();
+    if ARCHIVE_EXTENSIONS.iter().any(|ext| lower.ends_with(ext)) {
+        return Ok(SourceKind::Archive);
+    }
+    if !url_str.contains("://") && !url_str.starts_with("git@") {
+        return Ok(SourceKind::Local);
+    }
+    let remote = git_hosts::parse_remote_url(url_str, &git_hosts::get_hosts())?;
+    Ok(SourceKind::from(remote.kind))
+}
+
+/// Without a `source_type`, the kind is detected from `url_str`.
 #[tauri::command(rename_all = "snake_case")]
-// Renamed function and added source_type parameter
 pub fn verify_and_extract_source_info(
     url_str: &str,
-    source_type: i32,
+    source_type: Option<SourceKindArg>,
 ) -> Result<SourceInfo, String> {
+    let source_type = match source_type {
+        Some(source_type) => SourceKind::try_from(source_type)?,
+        None => detect_source_kind(url_str)?,
+    };
     match source_type {
-        0 => {
-            // GitHub
-            let url = url::Url::parse(url_str).map_err(|e| format!("Invalid GitHub URL: {e}"))?;
-            let host = url.host_str().unwrap_or_default();
-            if host != "github.com" && host != "www.github.com" {
-                return Err(String::from(
-                    "URL is not from GitHub (github.com or www.github.com)",
-                ));
-            }
-
-            let mut path_segments: Vec<&str> =
-                url.path_segments().map_or_else(Vec::new, |segments| {
-                    segments.filter(|s| !s.is_empty()).collect()
-                });
-
-            if path_segments.len() < 2 {
-                return Err(String::from(
-                    "GitHub URL must contain at least an owner and a repository name.",
-                ));
-            }
-
-            let owner = path_segments.remove(0).to_string();
-            let repo = path_segments
-                .remove(0)
-                .to_string()
-                .trim_end_matches(".git")
-                .to_string();
-
-            // GitHub username: alphanumeric or single hyphens, not at start/end.
-            // GitHub repo: alphanumeric, ., -, _
-            let owner_regex = Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9-]*[a-zA-Z0-9])?$").unwrap();
-            let repo_regex = Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap();
-            let no_double_hyphen = Regex::new(r"--").unwrap();
-
-            if !owner_regex.is_match(&owner) || no_double_hyphen.is_match(&owner) {
-                return Err(format!(
-                    "Invalid GitHub owner name: '{owner}'. Must be alphanumeric or single hyphens, not starting/ending with a hyphen, and no consecutive hyphens."
-                ));
-            }
-
-            if !repo_regex.is_match(&repo)
-                || repo.starts_with('-')
-                || repo.ends_with('-')
-                || repo.ends_with('.')
-                || no_double_hyphen.is_match(&repo)
-            {
-                return Err(format!(
-                    "Invalid GitHub repository name: '{repo}'. Contains invalid characters or patterns."
-                ));
-            }
-
-            Ok(SourceInfo {
-                owner,
-                repo,
-                source_type,
-            })
-        }
-        1 => {
-            // GitLab
-            let url = url::Url::parse(url_str).map_err(|e| format!("Invalid GitLab URL: {e}"))?;
-            let host = url.host_str().unwrap_or_default();
-
-            if host != "gitlab.com" && host != "www.gitlab.com" {
-                return Err(String::from(
-                    "URL is not from GitLab (gitlab.com or www.gitlab.com)",
-                ));
-            }
-
-            let mut path_segments: Vec<String> =
-                url.path_segments().map_or_else(Vec::new, |segments| {
-                    segments
-                        .filter(|s| !s.is_empty())
-                        .map(String::from)
-                        .collect()
-                });
-
-            if path_segments.is_empty() {
-                return Err(String::from("GitLab URL path is empty."));
-            }
-
-            let repo_name_with_git = path_segments.pop().unwrap_or_default();
-            let repo = repo_name_with_git.trim_end_matches(".git").to_string();
-
-            if path_segments.is_empty() {
-                return Err(String::from(
-                    "GitLab URL must contain at least a group/owner and a project name.",
-                ));
-            }
-
-            let owner = path_segments.join("/");
-
-            // GitLab names: letters, digits, emojis (not handled by simple regex), '_', '.', '-', '+'.
-            // Cannot start with '-' or end with '.', '.git', '.atom'.
-            let gitlab_name_part_regex =
-                Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9_.+-]*[a-zA-Z0-9])?$").unwrap();
-            let no_double_hyphen = Regex::new(r"--").unwrap();
-
-            for segment in owner.split('/') {
-                if !gitlab_name_part_regex.is_match(segment) || no_double_hyphen.is_match(segment) {
-                    return Err(format!("Invalid GitLab group/owner segment: '{segment}'."));
-                }
-            }
-
-            if !gitlab_name_part_regex.is_match(&repo)
-                || repo.is_empty()
-                || repo.ends_with('.')
-                || no_double_hyphen.is_match(&repo)
-            {
-                return Err(format!("Invalid GitLab project name: '{repo}'."));
-            }
-
-            Ok(SourceInfo {
-                owner,
-                repo,
-                source_type,
-            })
-        }
-        2 => {
-            // Local File Path
-            let path_obj = Path::new(url_str);
-
-            let file_or_dir_name = path_obj
-                .file_name()
-                .and_then(|name| name.to_str())
-                .map(String::from);
-
-            let repo = match file_or_dir_name {
-                Some(name) if !name.is_empty() => name,
-                _ => {
-                    // If it's a root path like "/" or "C:\" or just an invalid/empty path for filename
-                    if path_obj.is_absolute() && path_obj.parent().is_none() {
-                        // e.g. / or C:\
-                        url_str.to_string() // Use the whole string for root
-                    } else {
-                        return Err(format!(
-                            "Invalid local path or could not determine file/directory name: '{url_str}'."
-                        ));
-                    }
-                }
-            };
-
-            let parent_dir = path_obj
-                .parent()
-                .and_then(|p| p.to_str())
-                .map(String::from)
-                .unwrap_or_else(|| {
-                    if path_obj.is_absolute() && path_obj.parent().is_none() {
-                        "".to_string()
-                    } else {
-                        ".".to_string()
-                    }
-                }); // Use "." for relative paths with no explicit parent
-
-            if repo.is_empty()
-                && !(url_str == "/" || (url_str.ends_with(":\\") && url_str.len() == 3))
-            {
-                // Check for empty repo unless it's a root
-                return Err(format!("Local path is empty or invalid: '{url_str}'."));
-            }
-
-            Ok(SourceInfo {
-                owner: parent_dir,
-                repo,
-                source_type,
-            })
-        }
-        _ => Err(String::from(
-            "Invalid source type specified. Use 0 for GitHub, 1 for GitLab, 2 for Local File.",
-        )),
+        SourceKind::Archive => archive_source_info(url_str),
+        SourceKind::Local => local_source_info(url_str),
+        _ => remote_source_info(url_str, source_type),
     }
 }
+
+/// Where a repository from a git host is cloned to:
+/// `<data directory>/repositories/<source prefix>-<owner>-<repo>`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_clone_path(
+    source_type: SourceKindArg,
+    owner: &str,
+    repo: &str,
+) -> Result<String, String> {
+    let source_type = SourceKind::try_from(source_type)?;
+    if source_type == SourceKind::Local {
+        return Err("Local repositories are not cloned".to_string());
+    }
+    let prefix = source_type.clone_directory_prefix();
+    let name = match source_type {
+        // An archive's owner is the directory it was in on this machine
+        SourceKind::Archive => format!("{prefix}-{repo}"),
+        _ => {
+            // Nested GitLab groups and Azure DevOps organisation/project owners contain slashes
+            let owner = owner.replace(['/', '\\'], "-");
+            format!("{prefix}-{owner}-{repo}")
+        }
+    };
+
+    let path = Path::new(&get_working_directory().await)
+        .join("repositories")
+        .join(name);
+    Ok(path.to_string_lossy().to_string())
+}
@@ -1,3 +1,19 @@
 pub fn to_string<T: ToString>(obj: T) -> String {
     obj.to_string()
 }
+
+/// Appends the items that aren't in `list` yet, keeping their order.
+pub fn add_unique(list: &mut Vec<String>, items: &[String]) {
+    for item in items {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+/// A unix timestamp as a YYYY-MM-DD date, for prompts and reports.
+pub fn format_date(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,370 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::manifest::{read_manifest, save_manifest_file};
+use crate::utils::to_string;
+
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+fn default_retention_days() -> i64 {
+    DEFAULT_RETENTION_DAYS
+}
+
+/// A cohort of repositories, typically one unit in one semester (e.g. FIT3170 S1 2025).
+///
+/// The repositories of the active workspace live in the top-level `repository` array of the
+/// manifest so the frontend can keep treating the manifest as a flat list. Repositories of
+/// every other workspace are stashed in that workspace's own `repository` array.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub semester: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub repository: Vec<Value>,
+    #[serde(default = "default_retention_days")]
+    pub retention_days: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkspaceSummary {
+    pub id: String,
+    pub name: String,
+    pub unit: Option<String>,
+    pub semester: Option<String>,
+    pub archived: bool,
+    pub active: bool,
+    pub repository_count: usize,
+}
+
+impl Workspace {
+    fn new(id: String, name: String) -> Self {
+        Workspace {
+            id,
+            name,
+            unit: None,
+            semester: None,
+            archived: false,
+            created_at: Some(chrono::Utc::now().to_rfc3339()),
+            repository: Vec::new(),
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+pub fn get_workspaces(manifest: &Value) -> Result<Vec<Workspace>, String> {
+    match manifest.get("workspaces") {
+        Some(workspaces) => serde_json::from_value(workspaces.clone()).map_err(to_string),
+        None => Ok(Vec::new()),
+    }
+}
+
+// Indexing a Value that isn't an object panics, so every write goes through this
+fn manifest_object(manifest: &mut Value) -> Result<&mut serde_json::Map<String, Value>, String> {
+    manifest
+        .as_object_mut()
+        .ok_or("The manifest is not a JSON object".to_string())
+}
+
+pub fn set_workspaces(manifest: &mut Value, workspaces: &[Workspace]) -> Result<(), String> {
+    let workspaces = serde_json::to_value(workspaces).map_err(to_string)?;
+    manifest_object(manifest)?.insert("workspaces".to_string(), workspaces);
+    Ok(())
+}
+
+pub fn get_active_workspace_id(manifest: &Value) -> String {
+    manifest
+        .get("active_workspace")
+        .and_then(|a| a.as_str())
+        .unwrap_or(DEFAULT_WORKSPACE_ID)
+        .to_string()
+}
+
+/// Adds a default workspace to manifests created before workspaces existed.
+/// Returns true if the manifest was changed.
+pub fn ensure_default_workspace(manifest: &mut Value) -> Result<bool, String> {
+    let mut workspaces = get_workspaces(manifest)?;
+    let active_id = get_active_workspace_id(manifest);
+    let has_active = manifest.get("active_workspace").is_some();
+    let object = manifest_object(manifest)?;
+    let mut changed = false;
+
+    if workspaces.is_empty() {
+        workspaces.push(Workspace::new(
+            DEFAULT_WORKSPACE_ID.to_string(),
+            "Default".to_string(),
+        ));
+        changed = true;
+    }
+
+    if !workspaces.iter().any(|w| w.id == active_id) {
+        object.insert(
+            "active_workspace".to_string(),
+            Value::String(workspaces[0].id.clone()),
+        );
+        changed = true;
+    } else if !has_active {
+        object.insert("active_workspace".to_string(), Value::String(active_id));
+        changed = true;
+    }
+
+    if !object.contains_key("repository") {
+        object.insert("repository".to_string(), Value::Array(Vec::new()));
+        changed = true;
+    }
+
+    if changed {
+        set_workspaces(manifest, &workspaces)?;
+    }
+    Ok(changed)
+}
+
+fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-");
+
+    if slug.is_empty() {
+        String::from("workspace")
+    } else {
+        slug
+    }
+}
+
+fn summarise(workspace: &Workspace, active_id: &str, manifest: &Value) -> WorkspaceSummary {
+    let active = workspace.id == active_id;
+    let repository_count = if active {
+        manifest
+            .get("repository")
+            .and_then(|r| r.as_array())
+            .map_or(0, |r| r.len())
+    } else {
+        workspace.repository.len()
+    };
+
+    WorkspaceSummary {
+        id: workspace.id.clone(),
+        name: workspace.name.clone(),
+        unit: workspace.unit.clone(),
+        semester: workspace.semester.clone(),
+        archived: workspace.archived,
+        active,
+        repository_count,
+    }
+}
+
+async fn load_manifest() -> Result<Value, String> {
+    let mut manifest = read_manifest().await?;
+    ensure_default_workspace(&mut manifest)?;
+    Ok(manifest)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_workspaces(
+    include_archived: Option<bool>,
+) -> Result<Vec<WorkspaceSummary>, String> {
+    let manifest = load_manifest().await?;
+    let active_id = get_active_workspace_id(&manifest);
+    let include_archived = include_archived.unwrap_or(false);
+
+    Ok(get_workspaces(&manifest)?
+        .iter()
+        .filter(|w| include_archived || !w.archived)
+        .map(|w| summarise(w, &active_id, &manifest))
+        .collect())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn create_workspace(
+    name: &str,
+    unit: Option<String>,
+    semester: Option<String>,
+    retention_days: Option<i64>,
+) -> Result<Workspace, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    if retention_days.is_some_and(|d| d < 1) {
+        return Err("Retention period must be at least 1 day".to_string());
+    }
+
+    let mut workspace = Workspace::new(slugify(name), name.to_string());
+    workspace.unit = unit;
+    workspace.semester = semester;
+    workspace.retention_days = retention_days.unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    add_workspace(workspace).await
+}
+
+/// Adds `workspace` to the manifest, suffixing its id if another workspace already uses it.
+pub async fn add_workspace(mut workspace: Workspace) -> Result<Workspace, String> {
+    let mut manifest = load_manifest().await?;
+    let mut workspaces = get_workspaces(&manifest)?;
+
+    let base_id = workspace.id.clone();
+    let mut suffix = 2;
+    while workspaces.iter().any(|w| w.id == workspace.id) {
+        workspace.id = format!("{base_id}-{suffix}");
+        suffix += 1;
+    }
+
+    workspaces.push(workspace.clone());
+    set_workspaces(&mut manifest, &workspaces)?;
+    save_manifest_file(&manifest).await?;
+
+    log::info!("Created workspace {}", workspace.id);
+    Ok(workspace)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn update_workspace(
+    id: &str,
+    name: Option<String>,
+    unit: Option<String>,
+    semester: Option<String>,
+    retention_days: Option<i64>,
+) -> Result<Workspace, String> {
+    if retention_days.is_some_and(|d| d < 1) {
+        return Err("Retention period must be at least 1 day".to_string());
+    }
+
+    let mut manifest = load_manifest().await?;
+    let mut workspaces = get_workspaces(&manifest)?;
+    let workspace = workspaces
+        .iter_mut()
+        .find(|w| w.id == id)
+        .ok_or(format!("Workspace '{id}' not found"))?;
+
+    if let Some(name) = name {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Workspace name cannot be empty".to_string());
+        }
+        workspace.name = name.to_string();
+    }
+    // An empty string clears the unit or semester
+    if let Some(unit) = unit {
+        workspace.unit = Some(unit).filter(|u| !u.trim().is_empty());
+    }
+    if let Some(semester) = semester {
+        workspace.semester = Some(semester).filter(|s| !s.trim().is_empty());
+    }
+    if let Some(days) = retention_days {
+        workspace.retention_days = days;
+    }
+
+    let updated = workspace.clone();
+    set_workspaces(&mut manifest, &workspaces)?;
+    save_manifest_file(&manifest).await?;
+    Ok(updated)
+}
+
+/// Makes `id` the active workspace, stashing the current top-level repositories in the
+/// previously active workspace. Returns the updated manifest for the frontend store.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn switch_workspace(id: &str) -> Result<Value, String> {
+    let mut manifest = load_manifest().await?;
+    let active_id = get_active_workspace_id(&manifest);
+    if active_id == id {
+        return Ok(manifest);
+    }
+
+    let mut workspaces = get_workspaces(&manifest)?;
+    let target_idx = workspaces
+        .iter()
+        .position(|w| w.id == id)
+        .ok_or(format!("Workspace '{id}' not found"))?;
+    if workspaces[target_idx].archived {
+        return Err(format!(
+            "Workspace '{id}' is archived. Unarchive it before switching to it."
+        ));
+    }
+
+    let current_repos = match manifest.get("repository") {
+        Some(Value::Array(repos)) => repos.clone(),
+        _ => Vec::new(),
+    };
+    if let Some(current) = workspaces.iter_mut().find(|w| w.id == active_id) {
+        current.repository = current_repos;
+    }
+
+    let target_repos = std::mem::take(&mut workspaces[target_idx].repository);
+    let object = manifest_object(&mut manifest)?;
+    object.insert("repository".to_string(), Value::Array(target_repos));
+    object.insert(
+        "active_workspace".to_string(),
+        Value::String(id.to_string()),
+    );
+    set_workspaces(&mut manifest, &workspaces)?;
+    save_manifest_file(&manifest).await?;
+
+    log::info!("Switched workspace from {active_id} to {id}");
+    Ok(manifest)
+}
+
+/// Archived workspaces are hidden from the workspace list and skipped by the retention
+/// cleanup, so their repositories are kept until the workspace is unarchived.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn archive_workspace(id: &str, archived: Option<bool>) -> Result<(), String> {
+    let archived = archived.unwrap_or(true);
+    let mut manifest = load_manifest().await?;
+
+    if archived && get_active_workspace_id(&manifest) == id {
+        return Err("Cannot archive the active workspace. Switch to another one first.".into());
+    }
+
+    let mut workspaces = get_workspaces(&manifest)?;
+    let workspace = workspaces
+        .iter_mut()
+        .find(|w| w.id == id)
+        .ok_or(format!("Workspace '{id}' not found"))?;
+    workspace.archived = archived;
+
+    set_workspaces(&mut manifest, &workspaces)?;
+    save_manifest_file(&manifest).await
+}
+
+/// Returns the workspace with its repositories, regardless of whether it is active.
+pub async fn get_workspace(id: &str) -> Result<Workspace, String> {
+    let manifest = load_manifest().await?;
+    let mut workspace = get_workspaces(&manifest)?
+        .into_iter()
+        .find(|w| w.id == id)
+        .ok_or(format!("Workspace '{id}' not found"))?;
+
+    if get_active_workspace_id(&manifest) == id {
+        if let Some(Value::Array(repos)) = manifest.get("repository") {
+            workspace.repository = repos.clone();
+        }
+    }
+    Ok(workspace)
+}
+
+/// Writes the workspace, including its repository entries, to `destination` as JSON.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_workspace(id: &str, destination: &str) -> Result<(), String> {
+    let workspace = get_workspace(id).await?;
+    let content = serde_json::to_string_pretty(&workspace).map_err(to_string)?;
+    std::fs::write(destination, content).map_err(|e| {
+        log::error!("Failed to export workspace {id} to {destination}: {e}");
+        format!("Failed to export workspace: {e}")
+    })?;
+
+    log::info!("Exported workspace {id} to {destination}");
+    Ok(())
+}
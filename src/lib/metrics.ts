@@ -25,6 +25,22 @@ export type Contributor = Readonly<{
     total_regex_matches: number;
     commits_matching_regex: number;
     ai_summary: string;
+    forge_activity?: ForgeActivity | null;
+    /** Git host usernames */
+    logins?: string[];
+    /** Author names used in commits */
+    names?: string[];
+}>;
+
+/** Pull requests, reviews and issues from the git host */
+export type ForgeActivity = Readonly<{
+    pull_requests_opened: number;
+    pull_requests_merged: number;
+    reviews: number;
+    review_comments: number;
+    issues_opened: number;
+    issues_closed: number;
+    discussion_comments: number;
 }>;
 
 export type UserDisplayData = Readonly<{
@@ -35,12 +51,25 @@ export type UserDisplayData = Readonly<{
     offsetIndex?: number;
 }>;
 
+export type BranchInfo = Readonly<{
+    name: string;
+    remote: boolean;
+    default: boolean;
+    tip: string;
+    tip_time: number;
+    tip_author: string;
+    ahead: number | null;
+    behind: number | null;
+    merged: boolean | null;
+}>;
+
 // Load branches for a repository
 export async function load_branches(repo_path: string): Promise<string[]> {
     try {
-        const real_branches = await invoke<string[]>("get_branch_names", {
+        const branch_info = await invoke<BranchInfo[]>("get_branch_names", {
             path: repo_path,
         });
+        const real_branches = branch_info.map((branch) => branch.name);
         info("REAL BRANCHES " + real_branches);
         return ["All", ...real_branches];
     } catch (err) {
@@ -49,44 +78,213 @@ export async function load_branches(repo_path: string): Promise<string[]> {
     }
 }
 
-type DateRange = {
+/** Distinct authors in the whole history of `branch`. */
+export async function count_branch_contributors(
+    repo_path: string,
+    branch: string
+): Promise<number | undefined> {
+    try {
+        return await invoke<number>("count_branch_contributors", {
+            path: repo_path,
+            branch,
+        });
+    } catch (err) {
+        error("Failed to count branch contributors: " + err);
+        return undefined;
+    }
+}
+
+export type DateRange = {
     start: number;
     end: number;
 };
 
-export async function bare_clone(
-    source: string,
+/** Converts the "DD-MM-YY" calendar selection into the UNIX timestamp range used by the backend. */
+export function to_date_range(
+    start_date?: string,
+    end_date?: string
+): DateRange | undefined {
+    if (!start_date || !end_date) return undefined;
+    const start_ts = Math.floor(parse_date(start_date).getTime() / 1000);
+    const end_ts = Math.floor(parse_date(end_date).getTime() / 1000);
+    return { start: start_ts, end: end_ts };
+}
+
+/** Where a repository comes from */
+export type SourceKind = Readonly<{
+    kind:
+        | "github"
+        | "gitlab"
+        | "bitbucket"
+        | "gitea"
+        | "azure_devops"
+        | "local"
+        | "archive";
+}>;
+
+export type LocalRepoInfo = Readonly<{
+    root: string;
+    subdirectory: string | null;
+    bare: boolean;
+    worktree: boolean;
+    origin_url: string | null;
+    head_branch: string | null;
+    commit_count: number;
+}>;
+
+export type SourceInfo = Readonly<{
+    /** Owner or group on a git host, parent directory for local repositories */
+    owner: string;
+    repo: string;
+    source_type: SourceKind;
+    host: string | null;
+    clone_url: string | null;
+    target: unknown | null;
+    local: LocalRepoInfo | null;
+}>;
+
+// Integer codes older manifests and saved page state use
+const LEGACY_SOURCE_KINDS: SourceKind[] = [
+    { kind: "github" },
+    { kind: "gitlab" },
+    { kind: "local" },
+];
+
+/** Reads a source type saved as either a SourceKind or a legacy integer code */
+export function to_source_kind(
+    value: SourceKind | number | undefined
+): SourceKind {
+    if (typeof value === "number") {
+        return LEGACY_SOURCE_KINDS[value] ?? { kind: "github" };
+    }
+    return value ?? { kind: "github" };
+}
+
+/** Whether the repository is cloned from a git host rather than read from disk */
+export function is_remote(source_type: SourceKind): boolean {
+    return source_type.kind !== "local" && source_type.kind !== "archive";
+}
+
+/** The kind of a URL or path, without checking that the repository exists */
+export async function detect_source_kind(url: string): Promise<SourceKind> {
+    return await invoke<SourceKind>("detect_source_kind", { url_str: url });
+}
+
+/** Verifies a URL or path, detecting its kind unless `source_type` is given */
+export async function get_source_info(
+    url: string,
+    source_type?: SourceKind
+): Promise<SourceInfo> {
+    return await invoke<SourceInfo>("verify_and_extract_source_info", {
+        url_str: url,
+        source_type: source_type ?? null,
+    });
+}
+
+/** The repository's web URL, used to identify it in the manifest */
+export function repository_url(source_info: SourceInfo): string {
+    return (source_info.clone_url ?? "").replace(/\.git$/, "");
+}
+
+/** Where a repository from a git host is cloned to in the working directory */
+export async function get_clone_path(
+    source_type: SourceKind,
     owner: string,
-    repo: string,
-    source_type: 0 | 1 | 2,
-    depth?: number | null
+    repo: string
 ): Promise<string> {
-    const repo_url = `${source}/${owner}/${repo}`;
-    const working_dir = await invoke<string>("get_working_directory");
-    const repo_path = `${working_dir}/repositories/${source_type}-${owner}-${repo}`;
+    return await invoke<string>("get_clone_path", { source_type, owner, repo });
+}
 
-    const depth_value = depth && depth > 0 ? depth : null;
+/** Extracts a .zip export of a repository and returns the repository root */
+export async function import_archive(path: string): Promise<string> {
+    return await invoke<string>("import_archive", { path });
+}
+
+/** What the host's API says about a repository before it is cloned */
+export type RepoPreflight = Readonly<{
+    exists: boolean;
+    token_required: boolean;
+    visibility: "public" | "private" | "internal" | null;
+    default_branch: string | null;
+    size_kb: number | null;
+    last_push: string | null;
+    large: boolean;
+    suggested_depth: number | null;
+}>;
+
+/** Null when the host can't be asked, e.g. for hosts other than GitHub and GitLab */
+export async function preflight_remote_repository(
+    url: string
+): Promise<RepoPreflight | null> {
+    try {
+        return await invoke<RepoPreflight>("preflight_remote_repository", {
+            url,
+        });
+    } catch (err) {
+        info(`Skipping the preflight check for ${url}: ${err}`);
+        return null;
+    }
+}
+
+export type CloneResult = Readonly<{
+    path: string;
+    /** The depth used, which is the suggested one for large repositories if none was given */
+    depth: number | null;
+}>;
+
+export async function bare_clone(
+    source_info: SourceInfo,
+    depth?: number | null
+): Promise<CloneResult> {
+    const repo_url = source_info.clone_url ?? "";
+    const repo_path = await get_clone_path(
+        source_info.source_type,
+        source_info.owner,
+        source_info.repo
+    );
+
+    let depth_value = depth && depth > 0 ? depth : null;
+
+    if (await invoke<boolean>("is_repo_cloned", { path: repo_path })) {
+        return { path: repo_path, depth: depth_value };
+    }
+
+    const preflight = await preflight_remote_repository(repo_url);
+    if (preflight && !preflight.exists) {
+        if (preflight.token_required) {
+            show_token_modal(
+                "Repository is private and requires authentication.",
+                repo_url,
+                repo_path,
+                depth_value
+            );
+        }
+        throw new Error("Repository not found. Please check the URL.");
+    }
+    if (preflight?.suggested_depth && depth_value === null) {
+        info(
+            `${repo_url} is large, cloning the last ${preflight.suggested_depth} commits`
+        );
+        depth_value = preflight.suggested_depth;
+    }
 
     try {
-        const working_dir = await invoke<string>("get_working_directory");
-        const repo_path = `${working_dir}/repositories/${source_type}-${owner}-${repo}`;
         await invoke("bare_clone", {
             url: repo_url,
             path: repo_path,
             depth: depth_value,
         });
-        return repo_path;
+        return { path: repo_path, depth: depth_value };
     } catch (err) {
         const error_message = String(err);
         error(`Failed to clone the repository: ${error_message}`);
-        let repo_path: string;
 
-        // Check if this is an authentication error that requires a token
-        if (error_message.includes("remote authentication required")) {
-            const working_dir = await invoke<string>("get_working_directory");
-            repo_path = `${working_dir}/repositories/${source_type}-${owner}-${repo}`;
+        // Hosts without a preflight check only report missing credentials when cloning
+        if (
+            !preflight &&
+            error_message.includes("remote authentication required")
+        ) {
             show_token_modal(error_message, repo_url, repo_path, depth_value);
-            return repo_path;
         }
 
         throw new Error(error_message);
@@ -102,15 +300,7 @@ export async function load_commit_data(
 ): Promise<Contributor[]> {
     info(`Loading contributor data for ${repo_path}...`);
     try {
-        let date_range: DateRange | undefined = undefined;
-
-        if (start_date && end_date) {
-            const start_ts = Math.floor(
-                parse_date(start_date).getTime() / 1000
-            );
-            const end_ts = Math.floor(parse_date(end_date).getTime() / 1000);
-            date_range = { start: start_ts, end: end_ts }; // Send as object
-        }
+        const date_range = to_date_range(start_date, end_date); // Send as object
 
         const commit_data = await invoke<Contributor[]>(
             "get_contributor_info",
@@ -1,6 +1,12 @@
 import { invoke } from "@tauri-apps/api/core";
 import { writable, get } from "svelte/store";
 import { info } from "@tauri-apps/plugin-log";
+import {
+    get_clone_path,
+    is_remote,
+    to_source_kind,
+    type SourceKind,
+} from "$lib/metrics";
 
 export interface Config {
     [group: string]: string[];
@@ -15,7 +21,7 @@ export interface RepoSchema {
     last_accessed: string;
     name: string;
     owner: string;
-    source_type: 0 | 1 | 2; // 0 = GitHub, 1 = GitLab, 2 = Local
+    source_type: SourceKind;
     path: string;
     url: string;
     visited: boolean;
@@ -28,15 +34,22 @@ export interface ManifestSchema {
 export interface RepositoryInformation {
     owner: string;
     repo: string;
-    source_type: 0 | 1 | 2;
+    source_type: SourceKind;
 }
 
 // Accepts either a Manifest object or a raw array of repos and normalizes it
 type ManifestInput = ManifestSchema | RepoSchema[];
 
 function normalize(input: ManifestInput): ManifestSchema {
-    if (Array.isArray(input)) return { repository: input };
-    return input;
+    const manifest = Array.isArray(input) ? { repository: input } : input;
+    // Older manifests store the source type as an integer code
+    return {
+        ...manifest,
+        repository: manifest.repository.map((r) => ({
+            ...r,
+            source_type: to_source_kind(r.source_type),
+        })),
+    };
 }
 
 function create_manifest_store() {
@@ -54,7 +67,7 @@ function create_manifest_store() {
 
         /** Directly set a ManifestSchema (use replace() if you're not sure of the shape). */
         set(manifest: ManifestSchema) {
-            set(manifest);
+            set(normalize(manifest));
         },
 
         /** Add or update a repo by name (merges fields). */
@@ -152,19 +165,19 @@ function create_manifest_store() {
         async create_repository(
             repo_info: RepositoryInformation,
             repo_url: string,
-            source_type: 0 | 1 | 2,
+            source_type: SourceKind,
             repo_local_path: string,
             depth?: number | null
         ) {
             try {
-                const working_dir = await invoke<string>(
-                    "get_working_directory"
-                );
-                const repo_path =
-                    source_type === 2
-                        ? repo_local_path
-                        : `${working_dir}/repositories/${repo_info.source_type}-${repo_info.owner}-${repo_info.repo}`;
-                repo_url = source_type === 2 ? repo_local_path : repo_url;
+                const repo_path = is_remote(source_type)
+                    ? await get_clone_path(
+                          repo_info.source_type,
+                          repo_info.owner,
+                          repo_info.repo
+                      )
+                    : repo_local_path;
+                repo_url = is_remote(source_type) ? repo_url : repo_local_path;
 
                 const new_repo: RepoSchema = {
                     name: repo_info.repo,
@@ -173,7 +186,7 @@ function create_manifest_store() {
                     url: repo_url,
                     path: repo_path,
                     bookmarked: false,
-                    cloned: source_type !== 2,
+                    cloned: is_remote(source_type),
                     depth: depth && depth > 0 ? depth : null,
                     email_mapping: null,
                     grading_sheet: null,
@@ -1,4 +1,5 @@
 import { writable } from "svelte/store";
+import type { SourceKind } from "$lib/metrics";
 
 export type Repo = {
     repo_name: string;
@@ -40,11 +41,31 @@ export function set_repo_url(repo: string) {
 export interface RepoOption {
     label: string;
     icon: string;
-    source_type: 0 | 1 | 2;
+    source_type: SourceKind;
 }
 
 export const repo_options: RepoOption[] = [
-    { label: "GitHub", icon: "brand-github", source_type: 0 },
-    { label: "GitLab", icon: "brand-gitlab", source_type: 1 },
-    { label: "Local", icon: "folder-code", source_type: 2 },
+    { label: "GitHub", icon: "brand-github", source_type: { kind: "github" } },
+    { label: "GitLab", icon: "brand-gitlab", source_type: { kind: "gitlab" } },
+    {
+        label: "Bitbucket",
+        icon: "brand-bitbucket",
+        source_type: { kind: "bitbucket" },
+    },
+    { label: "Gitea", icon: "brand-git", source_type: { kind: "gitea" } },
+    {
+        label: "Azure DevOps",
+        icon: "brand-azure",
+        source_type: { kind: "azure_devops" },
+    },
+    { label: "Local", icon: "folder-code", source_type: { kind: "local" } },
+    { label: "Archive", icon: "file-zip", source_type: { kind: "archive" } },
 ];
+
+export function repo_option(source_type: SourceKind): RepoOption {
+    return (
+        repo_options.find(
+            (option) => option.source_type.kind === source_type.kind
+        ) ?? repo_options[0]
+    );
+}
@@ -28,21 +28,24 @@ export async function save_state(s: any) {
 }
 
 import type { RepositoryInformation } from "$lib/stores/manifest";
+import { get_clone_path, is_remote, type SourceKind } from "$lib/metrics";
 
 export async function generate_state_object(
-    working_dir: string,
     repository_information: RepositoryInformation,
     repo_url: string,
-    source_type: 0 | 1 | 2,
+    source_type: SourceKind,
     branches: string[],
     contributors: Contributor[],
     selected_branch: string = ""
 ) {
     return {
-        repo_path:
-            source_type === 2
-                ? repo_url
-                : `${working_dir}/repositories/${source_type}-${repository_information.owner}-${repository_information.repo}`,
+        repo_path: is_remote(source_type)
+            ? await get_clone_path(
+                  source_type,
+                  repository_information.owner,
+                  repository_information.repo
+              )
+            : repo_url,
         repo_url: repo_url,
         owner: repository_information.owner,
         repo: repository_information.repo,